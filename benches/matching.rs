@@ -0,0 +1,147 @@
+use {
+    chrono::{NaiveDate, TimeZone, Utc},
+    criterion::{criterion_group, criterion_main, BenchmarkId, Criterion},
+    office_space_split::{
+        location::Address,
+        matching::{MatchingEngine, MatchingHorizon},
+        object_storage::ObjectStorage,
+        pricing::{Currency, Price},
+        rental_space::AddRentalSpaceRequest,
+        user::{AddUserRequest, WorkspaceRequest},
+        Generator,
+    },
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    std::collections::BTreeSet,
+};
+
+const SIZES: [(usize, usize); 3] = [(100, 1_000), (1_000, 10_000), (10_000, 100_000)];
+
+/// Builds `nb_regions` cities with `nb_rental_spaces` rental spaces owned by
+/// 100 hosts and `nb_guests` guests in total, always the same for given sizes.
+/// There are no regions if `nb_regions` is 1.
+fn city(nb_rental_spaces: usize, nb_guests: usize, nb_regions: usize) -> ObjectStorage {
+    let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let mut storage = ObjectStorage::new().with_generator(Generator::deterministic(0, now));
+    let mut rng = StdRng::seed_from_u64(0);
+    let region = |i: usize| (nb_regions > 1).then(|| format!("City {}", i % nb_regions));
+
+    let hosts: Vec<String> = (0..100)
+        .map(|_| {
+            let request = AddUserRequest {
+                first_name: "Host".to_owned(),
+                last_name: "Doe".to_owned(),
+                workspace_request: None,
+            };
+            storage.create_user(request).unwrap().id_value().to_owned()
+        })
+        .collect();
+
+    for i in 0..nb_rental_spaces {
+        let nb_workstations = rng.gen_range(40..=180);
+        let request = AddRentalSpaceRequest {
+            name: format!("Office {}", i),
+            address: Address {
+                street: format!("{} Main St", i),
+                postal_code: "10001".to_owned(),
+                city: "Cityville".to_owned(),
+                country: "Country".to_owned(),
+                coordinates: None,
+            },
+            surface: nb_workstations * rng.gen_range(2..=3),
+            nb_workstations,
+            price_per_workstation: 300 + 50 * rng.gen_range(0..=10),
+            currency: Currency::default(),
+            owner_id: hosts[i % hosts.len()].clone(),
+            region: region(i),
+            amenities: BTreeSet::new(),
+        };
+        storage.create_rental_space(request).unwrap();
+    }
+
+    for i in 0..nb_guests {
+        let nb_workstations = rng.gen_range(5..=120);
+        let request = AddUserRequest {
+            first_name: "Guest".to_owned(),
+            last_name: "Doe".to_owned(),
+            workspace_request: Some(WorkspaceRequest {
+                nb_workstations,
+                max_nb_workstations: None,
+                budget: Price::total_yearly(
+                    nb_workstations * rng.gen_range(300..=900),
+                    Currency::default(),
+                ),
+                desired_start_date: None,
+                term_months: 12,
+                priority: 0,
+                region: region(i),
+                location: None,
+                required_amenities: BTreeSet::new(),
+                preferred_amenities: BTreeSet::new(),
+                renew: false,
+            }),
+        };
+        storage.create_user(request).unwrap();
+    }
+
+    storage
+}
+
+fn greedy_matching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("greedy_matching");
+    group.sample_size(10);
+    let horizon = MatchingHorizon::new(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), 2);
+
+    for (nb_rental_spaces, nb_guests) in SIZES {
+        let storage = city(nb_rental_spaces, nb_guests, 1);
+        let parameter = format!("{}x{}", nb_rental_spaces, nb_guests);
+
+        for with_subsplit in [false, true] {
+            let name = if with_subsplit {
+                "with_split"
+            } else {
+                "without_split"
+            };
+            group.bench_with_input(
+                BenchmarkId::new(name, &parameter),
+                &storage,
+                |b, storage| {
+                    b.iter(|| {
+                        MatchingEngine::new(storage)
+                            .with_horizon(horizon)
+                            .get_greedy_matchings(with_subsplit)
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn regional_matching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("regional_matching");
+    group.sample_size(10);
+    let horizon = MatchingHorizon::new(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), 2);
+    let (nb_rental_spaces, nb_guests) = SIZES[SIZES.len() - 1];
+    let storage = city(nb_rental_spaces, nb_guests, 20);
+
+    for nb_threads in [1, 4] {
+        group.bench_with_input(
+            BenchmarkId::new("with_split", format!("20_regions/{}_threads", nb_threads)),
+            &storage,
+            |b, storage| {
+                b.iter(|| {
+                    MatchingEngine::new(storage)
+                        .with_horizon(horizon)
+                        .with_threads(nb_threads)
+                        .get_greedy_matchings(true)
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, greedy_matching, regional_matching);
+criterion_main!(benches);
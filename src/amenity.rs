@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Feature of a rental space that guests may require or prefer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Amenity {
+    MeetingRooms,
+    /// Reachable without stairs, e.g. on the ground floor or with a lift.
+    StepFreeAccess,
+    Parking,
+    /// Open at any hour, every day of the week.
+    #[serde(rename = "access_24_7")]
+    Access24x7,
+    Kitchen,
+    Showers,
+    BikeStorage,
+    PhoneBooths,
+}
@@ -0,0 +1,27 @@
+use {
+    clap::Parser,
+    office_space_split::{http_api, persistence::JsonFileStore},
+};
+
+#[derive(Parser)]
+#[command(version, about = "Serve the office space matching API over HTTP")]
+struct Args {
+    /// Address to listen on
+    #[arg(
+        long,
+        env = "OFFICE_SPACE_SPLIT_ADDR",
+        default_value = "127.0.0.1:8080"
+    )]
+    addr: String,
+
+    /// Directory in which users, rental spaces and contracts are stored
+    #[arg(long, env = "OFFICE_SPACE_SPLIT_DATA_DIR", default_value = "data")]
+    data_dir: String,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let store = JsonFileStore::new(args.data_dir);
+    println!("Listening on http://{}", args.addr);
+    http_api::serve(&args.addr, &store)
+}
@@ -0,0 +1,370 @@
+use crate::{
+    contract::{ContractAction, InvalidTransition, Party},
+    matching::{
+        Algorithm, MatchingEngine, MatchingHorizon, OrderingPolicy, RenewalPolicy, ScoringWeights,
+        StableMatching,
+    },
+    object_storage::ObjectNotFound,
+    persistence::{self, JsonFileStore},
+    rental_space::AddRentalSpaceRequest,
+    report::{MatchingReport, MatchingsWithReport},
+    user::AddUserRequest,
+    Generator,
+};
+
+use {
+    anyhow::Context,
+    chrono::{DateTime, NaiveDate, NaiveTime, Utc},
+    clap::{Parser, Subcommand, ValueEnum},
+    serde::{de::DeserializeOwned, Serialize},
+    std::{
+        fmt, fs,
+        io::{self, Read},
+        path::PathBuf,
+        process::ExitCode,
+    },
+    validator::{Validate, ValidationErrors},
+};
+
+/// Exit code used when the command failed for a reason unrelated to its input,
+/// e.g. the data directory could not be written.
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code used when the input could not be parsed, failed validation,
+/// referenced an object that does not exist or requested a change of status
+/// that the contract does not allow.
+pub const EXIT_INVALID_INPUT: u8 = 65;
+
+#[derive(Parser)]
+#[command(version, about = "Match office space seekers with rental spaces")]
+pub struct Cli {
+    /// Directory in which users, rental spaces and contracts are stored
+    #[arg(long, env = "OFFICE_SPACE_SPLIT_DATA_DIR", default_value = "data")]
+    pub data_dir: PathBuf,
+
+    /// Command to run. The interactive interface is started if omitted.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Add a user described by a JSON request and print it
+    AddUser {
+        /// JSON file to read the request from, stdin if omitted or "-"
+        input: Option<PathBuf>,
+    },
+    /// Add a rental space described by a JSON request and print it
+    AddRentalSpace {
+        /// JSON file to read the request from, stdin if omitted or "-"
+        input: Option<PathBuf>,
+    },
+    /// Print stored objects of the given kind
+    List { kind: ObjectKind },
+    /// Match users with rental spaces and print the contracts, storing them
+    /// in incremental mode
+    Match {
+        /// Allow splitting rental spaces between multiple users
+        #[arg(long)]
+        split: bool,
+        #[arg(long, value_enum, default_value_t = Algorithm::Greedy)]
+        algorithm: Algorithm,
+        /// Order in which the greedy, packing and scoring algorithms consider guests
+        #[arg(long, value_enum, default_value_t = OrderingPolicy::FirstComeFirstServed)]
+        ordering: OrderingPolicy,
+        /// Start date of the first period, today if omitted
+        #[arg(long)]
+        start_date: Option<NaiveDate>,
+        /// Number of periods to match
+        #[arg(long, default_value_t = 2)]
+        periods: u32,
+        /// Length of every period in months
+        #[arg(long, default_value_t = 12)]
+        period_months: u32,
+        /// Print the quality report of the matching along with the contracts
+        #[arg(long)]
+        report: bool,
+        /// Seed of the generated ids, for reproducible contracts. Contracts
+        /// are then timestamped at midnight UTC on the start date.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Keep the proposed, accepted and active contracts, only match the
+        /// remaining workstations with the guests that have none, and store
+        /// the generated contracts
+        #[arg(long)]
+        incremental: bool,
+        /// List the pairs of guest and rental space that would both rather be
+        /// leased to each other, as ranked by the stable algorithm
+        #[arg(long)]
+        blocking_pairs: bool,
+        /// Renew the contracts of the guests who asked for it when they end
+        #[arg(long)]
+        renew: bool,
+        /// Percentage by which the price per workstation rises at every renewal
+        #[arg(long, default_value_t = 0.0, requires = "renew")]
+        indexation_percent: f64,
+        /// Number of regions matched concurrently, the number of CPUs if omitted
+        #[arg(long)]
+        threads: Option<usize>,
+        /// Weight of the distance to the area preferred by the guest when scoring
+        #[arg(long, default_value_t = ScoringWeights::default().distance)]
+        distance_weight: f64,
+        /// Weight of the price relative to the budget when scoring
+        #[arg(long, default_value_t = ScoringWeights::default().price)]
+        price_weight: f64,
+        /// Weight of the share of workstations leased beyond the request when scoring
+        #[arg(long, default_value_t = ScoringWeights::default().waste)]
+        waste_weight: f64,
+        /// Weight of the share of preferred amenities missing when scoring
+        #[arg(long, default_value_t = ScoringWeights::default().amenities)]
+        amenity_weight: f64,
+    },
+    /// Print the waitlist, or the position of a user in it
+    Waitlist {
+        /// Id of the user, every waitlisted user if omitted
+        user_id: Option<String>,
+    },
+    /// Move a contract to another status and print it
+    Contract {
+        /// Id of the contract
+        id: String,
+        action: Transition,
+        /// Party accepting or rejecting the contract
+        #[arg(long, value_enum)]
+        by: Option<Party>,
+    },
+    /// Add all objects of an exported document to the storage
+    Import {
+        /// File to read the document from, stdin if omitted or "-"
+        input: Option<PathBuf>,
+    },
+    /// Print all stored objects as a single document
+    Export,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ObjectKind {
+    Users,
+    RentalSpaces,
+    Contracts,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Transition {
+    /// Agree to a proposed contract, which is accepted once both parties did
+    Accept,
+    /// Decline a proposed contract
+    Reject,
+    /// Start an accepted contract
+    Activate,
+    /// End an active contract on or after its end date
+    Expire,
+    /// End an active contract before its end date
+    Terminate,
+}
+
+impl Transition {
+    /// Returns the action to apply, `by` being required to accept or reject.
+    fn action(self, by: Option<Party>) -> anyhow::Result<ContractAction> {
+        let by = || {
+            by.context("--by is required to accept or reject a contract")
+                .context(InvalidInput)
+        };
+        Ok(match self {
+            Transition::Accept => ContractAction::Accept { by: by()? },
+            Transition::Reject => ContractAction::Reject { by: by()? },
+            Transition::Activate => ContractAction::Activate,
+            Transition::Expire => ContractAction::Expire,
+            Transition::Terminate => ContractAction::Terminate,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorOutput {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct ImportOutput {
+    users: usize,
+    rental_spaces: usize,
+    contracts: usize,
+    waitlist: usize,
+}
+
+/// Marks errors caused by malformed input rather than by the command itself.
+#[derive(Debug)]
+struct InvalidInput;
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid input")
+    }
+}
+
+/// Runs a single command against the storage kept in the data directory.
+/// The result is printed to stdout as JSON, errors are printed to stderr.
+pub fn run(command: Command, store: &JsonFileStore) -> ExitCode {
+    match execute(command, store) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            let output = ErrorOutput {
+                error: format!("{:#}", e),
+            };
+            eprintln!("{}", serde_json::to_string(&output).unwrap());
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+fn execute(command: Command, store: &JsonFileStore) -> anyhow::Result<String> {
+    let mut storage = store.load()?;
+
+    let output = match command {
+        Command::AddUser { input } => {
+            let request: AddUserRequest = parse_input(input)?;
+            serde_json::to_string_pretty(storage.create_user(request)?)?
+        }
+        Command::AddRentalSpace { input } => {
+            let request: AddRentalSpaceRequest = parse_input(input)?;
+            serde_json::to_string_pretty(storage.create_rental_space(request)?)?
+        }
+        Command::List { kind } => {
+            return Ok(match kind {
+                ObjectKind::Users => serde_json::to_string_pretty(&storage.users())?,
+                ObjectKind::RentalSpaces => serde_json::to_string_pretty(&storage.rental_spaces())?,
+                ObjectKind::Contracts => serde_json::to_string_pretty(&storage.contracts())?,
+            });
+        }
+        Command::Match {
+            split,
+            algorithm,
+            ordering,
+            start_date,
+            periods,
+            period_months,
+            report,
+            seed,
+            incremental,
+            blocking_pairs,
+            renew,
+            indexation_percent,
+            threads,
+            distance_weight,
+            price_weight,
+            waste_weight,
+            amenity_weight,
+        } => {
+            let start_date = start_date.unwrap_or(MatchingHorizon::default().start_date);
+            let horizon =
+                MatchingHorizon::new(start_date, periods).with_period_months(period_months);
+            horizon.validate()?;
+            let mut matching_engine = MatchingEngine::new(&storage)
+                .with_horizon(horizon)
+                .with_ordering(ordering)
+                .with_incremental(incremental);
+            let weights = ScoringWeights {
+                distance: distance_weight,
+                price: price_weight,
+                waste: waste_weight,
+                amenities: amenity_weight,
+            };
+            if blocking_pairs {
+                matching_engine =
+                    matching_engine.with_stability_check(StableMatching::new(weights));
+            }
+            if renew {
+                let renewal = RenewalPolicy::new(indexation_percent);
+                renewal.validate()?;
+                matching_engine = matching_engine.with_renewal(renewal);
+            }
+            if let Some(threads) = threads {
+                matching_engine = matching_engine.with_threads(threads);
+            }
+            if let Some(seed) = seed {
+                matching_engine = matching_engine
+                    .with_generator(Generator::deterministic(seed, midnight_utc(start_date)));
+            }
+            let matchings = matching_engine.get_matchings(algorithm, split, weights);
+            let output = if report {
+                serde_json::to_string_pretty(&MatchingsWithReport {
+                    matchings: &matchings,
+                    report: MatchingReport::new(&storage, &matchings),
+                })?
+            } else {
+                serde_json::to_string_pretty(&matchings)?
+            };
+            if incremental {
+                storage.store_matchings(&matchings);
+            }
+            output
+        }
+        Command::Waitlist { user_id } => {
+            return Ok(match user_id {
+                Some(user_id) => {
+                    serde_json::to_string_pretty(&storage.get_waitlist_position(&user_id).ok_or(
+                        ObjectNotFound {
+                            kind: "Waitlisted user",
+                            id: user_id,
+                        },
+                    )?)?
+                }
+                None => serde_json::to_string_pretty(&storage.waitlist_positions())?,
+            });
+        }
+        Command::Contract { id, action, by } => {
+            serde_json::to_string_pretty(storage.transition_contract(&id, action.action(by)?)?)?
+        }
+        Command::Import { input } => {
+            let imported = persistence::import(&read_input(input)?).context(InvalidInput)?;
+            let output = ImportOutput {
+                users: imported.users().len(),
+                rental_spaces: imported.rental_spaces().len(),
+                contracts: imported.contracts().len(),
+                waitlist: imported.waitlist().entries().len(),
+            };
+            storage.merge(imported);
+            serde_json::to_string_pretty(&output)?
+        }
+        Command::Export => return persistence::export(&storage),
+    };
+
+    store.save(&storage)?;
+    Ok(output)
+}
+
+fn midnight_utc(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+fn parse_input<T: DeserializeOwned>(input: Option<PathBuf>) -> anyhow::Result<T> {
+    serde_json::from_str(&read_input(input)?).context(InvalidInput)
+}
+
+fn read_input(input: Option<PathBuf>) -> anyhow::Result<String> {
+    match input {
+        Some(path) if path.as_os_str() != "-" => Ok(fs::read_to_string(path)?),
+        _ => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            Ok(content)
+        }
+    }
+}
+
+fn exit_code(error: &anyhow::Error) -> u8 {
+    let is_invalid_input = error.downcast_ref::<InvalidInput>().is_some()
+        || error.chain().any(|cause| {
+            cause.is::<ValidationErrors>()
+                || cause.is::<ObjectNotFound>()
+                || cause.is::<InvalidTransition>()
+        });
+    if is_invalid_input {
+        EXIT_INVALID_INPUT
+    } else {
+        EXIT_FAILURE
+    }
+}
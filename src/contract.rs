@@ -1,45 +1,400 @@
-use std::fmt;
+use {
+    chrono::{DateTime, Months, NaiveDate, Utc},
+    clap::ValueEnum,
+    serde::{Deserialize, Serialize},
+    std::fmt,
+    validator::Validate,
+};
 
-use crate::{rental_space::RentalSpaceId, user::UserId, BaseFields, PrefixedUuid};
+use crate::{
+    pricing::{Currency, Price},
+    rental_space::{RentalSpaceId, Split},
+    user::UserId,
+    BaseFields, Generator, PrefixedUuid,
+};
 
+/// Contracts loaded or imported must lease workstations for at least a month.
+#[derive(Clone, Serialize, Deserialize, Validate)]
 pub struct Contract {
+    #[serde(flatten)]
     base: BaseFields<ContractId>,
     rental_space_id: RentalSpaceId,
     host_id: UserId,
     guest_id: UserId,
+    #[validate(range(min = 1))]
     nb_workstations: u32,
     price: u32,
+    #[serde(default)]
+    currency: Currency,
+    /// The price in the same terms as the budget of the guest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quoted_price: Option<Price>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    #[validate(range(min = 1))]
+    term_months: u32,
+    status: ContractStatus,
+    /// Every change of status, oldest first, starting with the proposal.
+    history: Vec<StatusChange>,
+    /// The contract this one continues, if it is a renewal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    renewed_from: Option<ContractId>,
 }
 
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct ContractId {
     value: String,
 }
 
 impl PrefixedUuid for ContractId {
     const PREFIX: &'static str = "agr";
+
+    fn from_value(value: String) -> Self {
+        Self { value }
+    }
+}
+
+/// Stage in the life of a contract. Contracts are proposed by the matching,
+/// accepted once both the host and the guest agreed to them, and active while
+/// the guest occupies the workstations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractStatus {
+    Proposed,
+    Accepted,
+    Rejected,
+    Active,
+    Expired,
+    Terminated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Party {
+    Host,
+    Guest,
+}
+
+/// Request to move a contract to another status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ContractAction {
+    /// One of the parties agrees to a proposed contract, which is accepted
+    /// once both of them did.
+    Accept { by: Party },
+    /// One of the parties declines a proposed contract.
+    Reject { by: Party },
+    /// The guest moves in under an accepted contract.
+    Activate,
+    /// An active contract reached its end date.
+    Expire,
+    /// An active contract is ended before its end date, which becomes the day
+    /// of the termination.
+    Terminate,
+}
+
+/// Entry of the history of a contract.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub status: ContractStatus,
+    /// The party that accepted or rejected the contract, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<Party>,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct InvalidTransition {
+    pub id: String,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Contract with id {} {}", self.id, self.reason)
+    }
 }
 
+impl std::error::Error for InvalidTransition {}
+
 impl Contract {
+    /// Creates a contract leasing the whole `split` to the guest, quoting its
+    /// price in the same terms as `budget`.
     pub fn new(
-        rental_space_id: RentalSpaceId,
-        host_id: UserId,
+        split: &Split,
         guest_id: UserId,
-        nb_workstations: u32,
-        price: u32,
+        budget: &Price,
+        start_date: NaiveDate,
+        term_months: u32,
+        generator: &Generator,
     ) -> Self {
         Self {
-            base: BaseFields::new(ContractId {
-                value: ContractId::generate(),
-            }),
-            rental_space_id,
-            host_id,
+            base: BaseFields::new(generator),
+            rental_space_id: split.parent_office_id.clone(),
+            host_id: split.owner_id.clone(),
             guest_id,
-            nb_workstations,
-            price,
+            nb_workstations: split.nb_workstations,
+            price: split.price(),
+            currency: split.currency().clone(),
+            quoted_price: Some(budget.quote(split.nb_workstations, split.price_per_workstation)),
+            start_date,
+            end_date: start_date + Months::new(term_months),
+            term_months,
+            status: ContractStatus::Proposed,
+            history: vec![StatusChange {
+                status: ContractStatus::Proposed,
+                by: None,
+                at: generator.now(),
+            }],
+            renewed_from: None,
+        }
+    }
+
+    /// Creates the contract continuing this one from `start_date`, leasing
+    /// the same workstations with their price raised by
+    /// `indexation_percent`, rounded to the nearest whole amount.
+    pub fn renewal(
+        &self,
+        budget: &Price,
+        start_date: NaiveDate,
+        term_months: u32,
+        indexation_percent: f64,
+        generator: &Generator,
+    ) -> Self {
+        let price_per_workstation = (self.price_per_workstation() as f64
+            * (1.0 + indexation_percent / 100.0))
+            .round() as u32;
+        Self {
+            base: BaseFields::new(generator),
+            rental_space_id: self.rental_space_id.clone(),
+            host_id: self.host_id.clone(),
+            guest_id: self.guest_id.clone(),
+            nb_workstations: self.nb_workstations,
+            price: self.nb_workstations.saturating_mul(price_per_workstation),
+            currency: self.currency.clone(),
+            quoted_price: Some(budget.quote(self.nb_workstations, price_per_workstation)),
+            start_date,
+            end_date: start_date + Months::new(term_months),
+            term_months,
+            status: ContractStatus::Proposed,
+            history: vec![StatusChange {
+                status: ContractStatus::Proposed,
+                by: None,
+                at: generator.now(),
+            }],
+            renewed_from: Some(self.id().clone()),
+        }
+    }
+
+    pub fn id(&self) -> &ContractId {
+        &self.base.id
+    }
+
+    pub fn id_value(&self) -> &str {
+        &self.id().value
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.base.created_at
+    }
+
+    pub fn rental_space_id(&self) -> &RentalSpaceId {
+        &self.rental_space_id
+    }
+
+    pub fn host_id(&self) -> &UserId {
+        &self.host_id
+    }
+
+    pub fn guest_id(&self) -> &UserId {
+        &self.guest_id
+    }
+
+    pub fn nb_workstations(&self) -> u32 {
+        self.nb_workstations
+    }
+
+    /// Price paid by the guest per year.
+    pub fn price(&self) -> u32 {
+        self.price
+    }
+
+    /// Price of a single workstation per year.
+    pub fn price_per_workstation(&self) -> u32 {
+        self.price / self.nb_workstations
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    /// Price in the same terms as the budget of the guest. Contracts created
+    /// before budgets had terms have none.
+    pub fn quoted_price(&self) -> Option<&Price> {
+        self.quoted_price.as_ref()
+    }
+
+    /// Price paid by the guest over the whole term of the contract.
+    pub fn total_price(&self) -> u64 {
+        self.price as u64 * self.term_months as u64 / 12
+    }
+
+    pub fn term_months(&self) -> u32 {
+        self.term_months
+    }
+
+    pub fn start_date(&self) -> NaiveDate {
+        self.start_date
+    }
+
+    /// First day on which the workstations are no longer leased.
+    pub fn end_date(&self) -> NaiveDate {
+        self.end_date
+    }
+
+    pub fn is_active_on(&self, date: NaiveDate) -> bool {
+        self.start_date <= date && date < self.end_date
+    }
+
+    pub fn status(&self) -> ContractStatus {
+        self.status
+    }
+
+    pub fn history(&self) -> &[StatusChange] {
+        &self.history
+    }
+
+    pub fn renewed_from(&self) -> Option<&ContractId> {
+        self.renewed_from.as_ref()
+    }
+
+    /// Whether `party` accepted the contract while it was proposed.
+    pub fn is_accepted_by(&self, party: Party) -> bool {
+        match self.status {
+            ContractStatus::Proposed => self.history.iter().any(|change| change.by == Some(party)),
+            ContractStatus::Rejected => false,
+            _ => true,
+        }
+    }
+
+    /// Applies `action` at time `at`, recording the change in the history.
+    /// The contract is left unchanged if the action is not allowed in its
+    /// current status.
+    pub fn transition(
+        &mut self,
+        action: ContractAction,
+        at: DateTime<Utc>,
+    ) -> Result<(), InvalidTransition> {
+        let date = at.date_naive();
+        let (status, by) = match (self.status, action) {
+            (ContractStatus::Proposed, ContractAction::Accept { by }) => {
+                if self.is_accepted_by(by) {
+                    return Err(self.invalid(format!("was already accepted by the {}", by)));
+                }
+                if self.is_accepted_by(by.other()) {
+                    (ContractStatus::Accepted, Some(by))
+                } else {
+                    (ContractStatus::Proposed, Some(by))
+                }
+            }
+            (ContractStatus::Proposed, ContractAction::Reject { by }) => {
+                (ContractStatus::Rejected, Some(by))
+            }
+            (ContractStatus::Accepted, ContractAction::Activate) => (ContractStatus::Active, None),
+            (ContractStatus::Active, ContractAction::Expire) => {
+                if date < self.end_date {
+                    return Err(self.invalid(format!(
+                        "runs until {} and cannot expire yet",
+                        self.end_date
+                    )));
+                }
+                (ContractStatus::Expired, None)
+            }
+            (ContractStatus::Active, ContractAction::Terminate) => {
+                if date >= self.end_date {
+                    return Err(self.invalid(format!(
+                        "already ended on {}, expire it instead",
+                        self.end_date
+                    )));
+                }
+                self.end_date = std::cmp::max(date, self.start_date);
+                (ContractStatus::Terminated, None)
+            }
+            (status, action) => {
+                return Err(self.invalid(format!("is {} and cannot {}", status, action)));
+            }
+        };
+
+        self.status = status;
+        self.history.push(StatusChange { status, by, at });
+        Ok(())
+    }
+
+    fn invalid(&self, reason: String) -> InvalidTransition {
+        InvalidTransition {
+            id: self.id_value().to_owned(),
+            reason,
+        }
+    }
+}
+
+impl ContractStatus {
+    /// Whether the workstations of the contract are taken, or promised to the
+    /// guest while the contract is pending.
+    pub fn holds_workstations(self) -> bool {
+        matches!(
+            self,
+            ContractStatus::Proposed | ContractStatus::Accepted | ContractStatus::Active
+        )
+    }
+}
+
+impl Party {
+    pub fn other(self) -> Party {
+        match self {
+            Party::Host => Party::Guest,
+            Party::Guest => Party::Host,
+        }
+    }
+}
+
+impl fmt::Display for ContractStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            ContractStatus::Proposed => "proposed",
+            ContractStatus::Accepted => "accepted",
+            ContractStatus::Rejected => "rejected",
+            ContractStatus::Active => "active",
+            ContractStatus::Expired => "expired",
+            ContractStatus::Terminated => "terminated",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+impl fmt::Display for Party {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Party::Host => write!(f, "host"),
+            Party::Guest => write!(f, "guest"),
         }
     }
 }
 
+impl fmt::Display for ContractAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self {
+            ContractAction::Accept { .. } => "be accepted",
+            ContractAction::Reject { .. } => "be rejected",
+            ContractAction::Activate => "be activated",
+            ContractAction::Expire => "expire",
+            ContractAction::Terminate => "be terminated",
+        };
+        write!(f, "{}", action)
+    }
+}
+
 impl fmt::Debug for ContractId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.value)
@@ -56,14 +411,125 @@ impl fmt::Debug for Contract {
     host_id: {:?},
     guest_id: {:?},
     nb_workstations: {:?},
-    price: {:?}
+    price: {:?},
+    currency: {:?},
+    quoted_price: {:?},
+    start_date: {},
+    end_date: {},
+    term_months: {},
+    status: {},
+    history: {:?},
+    renewed_from: {:?}
 }}",
             self.base,
             self.rental_space_id,
             self.host_id,
             self.guest_id,
             self.nb_workstations,
-            self.price
+            self.price,
+            self.currency,
+            self.quoted_price,
+            self.start_date,
+            self.end_date,
+            self.term_months,
+            self.status,
+            self.history,
+            self.renewed_from
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono::TimeZone, serde_json::json};
+
+    fn proposed_contract() -> Contract {
+        serde_json::from_value(json!({
+            "id": "agr-1",
+            "created_at": "2025-01-01T00:00:00Z",
+            "rental_space_id": "ofc-1",
+            "host_id": "usr-1",
+            "guest_id": "usr-2",
+            "nb_workstations": 40,
+            "price": 16000,
+            "start_date": "2025-01-01",
+            "end_date": "2026-01-01",
+            "term_months": 12,
+            "status": "proposed",
+            "history": [{"status": "proposed", "at": "2025-01-01T00:00:00Z"}],
+        }))
+        .unwrap()
+    }
+
+    fn active_contract() -> Contract {
+        let mut contract = proposed_contract();
+        for action in [
+            ContractAction::Accept { by: Party::Host },
+            ContractAction::Accept { by: Party::Guest },
+            ContractAction::Activate,
+        ] {
+            contract.transition(action, at(2025, 1, 1)).unwrap();
+        }
+        contract
+    }
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn cannot_be_accepted_twice_by_the_same_party() {
+        let mut contract = proposed_contract();
+        let accept = ContractAction::Accept { by: Party::Host };
+        contract.transition(accept, at(2025, 1, 1)).unwrap();
+
+        let error = contract.transition(accept, at(2025, 1, 2)).unwrap_err();
+        assert_eq!(error.reason, "was already accepted by the host");
+        assert_eq!(contract.status(), ContractStatus::Proposed);
+        assert_eq!(contract.history().len(), 2);
+    }
+
+    #[test]
+    fn cannot_expire_before_its_end_date() {
+        let mut contract = active_contract();
+
+        let error = contract
+            .transition(ContractAction::Expire, at(2025, 12, 31))
+            .unwrap_err();
+        assert_eq!(error.reason, "runs until 2026-01-01 and cannot expire yet");
+        assert_eq!(contract.status(), ContractStatus::Active);
+
+        contract
+            .transition(ContractAction::Expire, at(2026, 1, 1))
+            .unwrap();
+        assert_eq!(contract.status(), ContractStatus::Expired);
+    }
+
+    #[test]
+    fn cannot_be_terminated_after_its_end_date() {
+        let mut contract = active_contract();
+
+        let error = contract
+            .transition(ContractAction::Terminate, at(2026, 1, 1))
+            .unwrap_err();
+        assert_eq!(
+            error.reason,
+            "already ended on 2026-01-01, expire it instead"
+        );
+        assert_eq!(contract.status(), ContractStatus::Active);
+        assert_eq!(
+            contract.end_date(),
+            NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn cannot_skip_statuses() {
+        let mut contract = proposed_contract();
+
+        let error = contract
+            .transition(ContractAction::Activate, at(2025, 1, 1))
+            .unwrap_err();
+        assert_eq!(error.reason, "is proposed and cannot be activated");
+    }
+}
@@ -0,0 +1,588 @@
+use crate::{
+    contract::{ContractAction, InvalidTransition},
+    matching::{
+        Algorithm, MatchingEngine, MatchingHorizon, OrderingPolicy, RenewalPolicy, ScoringWeights,
+        StableMatching,
+    },
+    object_storage::{ObjectNotFound, ObjectStorage},
+    persistence::JsonFileStore,
+    rental_space::AddRentalSpaceRequest,
+    report::{MatchingReport, MatchingsWithReport},
+    user::AddUserRequest,
+    Generator,
+};
+
+use {
+    chrono::NaiveTime,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    tiny_http::{Header, Method, Response, Server},
+    validator::{Validate, ValidationErrors},
+};
+
+/// Number of assignments the optimal algorithms explore in every period of a
+/// request, lower than for the command-line interface as requests are handled
+/// one at a time.
+pub const MAX_NODES: u64 = 100_000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MatchingRequest {
+    #[serde(default)]
+    pub with_subsplit: bool,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub ordering: OrderingPolicy,
+    /// Weights of the scoring algorithm and of the preferences of guests in
+    /// the stable algorithm, each criterion weighing 1 if omitted.
+    #[serde(default)]
+    pub weights: ScoringWeights,
+    #[serde(default)]
+    pub horizon: Option<MatchingHorizon>,
+    /// Keep the proposed, accepted and active contracts, see
+    /// [`MatchingEngine::with_incremental`]. Only the contracts of incremental
+    /// matchings are stored.
+    #[serde(default)]
+    pub incremental: bool,
+    /// List the blocking pairs of every period, see
+    /// [`MatchingEngine::with_stability_check`].
+    #[serde(default)]
+    pub blocking_pairs: bool,
+    /// Renew the contracts of the guests who asked for it, see
+    /// [`MatchingEngine::with_renewal`].
+    #[serde(default)]
+    pub renewal: Option<RenewalPolicy>,
+    #[serde(default)]
+    pub report: bool,
+    /// Seed of the generated ids. When set, contracts are timestamped at
+    /// midnight UTC on the start date of the horizon, so that the same request
+    /// over the same data always produces the same contracts.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: Option<Value>,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    InvalidJson(serde_json::Error),
+    NotFound(String),
+    Conflict(String),
+    Validation(ValidationErrors),
+    UnknownReference(ObjectNotFound),
+    MethodNotAllowed,
+    Internal(anyhow::Error),
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<&'a ValidationErrors>,
+}
+
+impl ApiResponse {
+    fn ok(body: impl Serialize) -> Result<Self, ApiError> {
+        Self::with_status(200, body)
+    }
+
+    fn created(body: impl Serialize) -> Result<Self, ApiError> {
+        Self::with_status(201, body)
+    }
+
+    fn no_content() -> Result<Self, ApiError> {
+        Ok(Self {
+            status: 204,
+            body: None,
+        })
+    }
+
+    fn with_status(status: u16, body: impl Serialize) -> Result<Self, ApiError> {
+        Ok(Self {
+            status,
+            body: Some(serde_json::to_value(body).map_err(|e| ApiError::Internal(e.into()))?),
+        })
+    }
+}
+
+impl From<ApiError> for ApiResponse {
+    fn from(error: ApiError) -> Self {
+        let (status, code, message, fields) = match &error {
+            ApiError::InvalidJson(e) => (400, "invalid_json", e.to_string(), None),
+            ApiError::NotFound(message) => (404, "not_found", message.clone(), None),
+            ApiError::MethodNotAllowed => (
+                405,
+                "method_not_allowed",
+                "Method not allowed".to_owned(),
+                None,
+            ),
+            ApiError::Conflict(message) => (409, "conflict", message.clone(), None),
+            ApiError::Validation(e) => (422, "validation_failed", e.to_string(), Some(e)),
+            ApiError::UnknownReference(e) => (422, "unknown_reference", e.to_string(), None),
+            ApiError::Internal(e) => (500, "internal_error", format!("{:#}", e), None),
+        };
+        Self {
+            status,
+            body: Some(
+                serde_json::to_value(ErrorBody {
+                    error: code,
+                    message,
+                    fields,
+                })
+                .unwrap(),
+            ),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<ValidationErrors>() {
+            Ok(e) => return ApiError::Validation(e),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<InvalidTransition>() {
+            Ok(e) => return ApiError::Conflict(e.to_string()),
+            Err(error) => error,
+        };
+        match error.downcast::<ObjectNotFound>() {
+            Ok(e) => ApiError::UnknownReference(e),
+            Err(error) => ApiError::Internal(error),
+        }
+    }
+}
+
+/// Serves the API on `addr` until the process is stopped. Requests are handled
+/// one at a time, see [`handle_and_save`].
+pub fn serve(addr: &str, store: &JsonFileStore) -> anyhow::Result<()> {
+    let mut storage = store.load()?;
+    let server = Server::http(addr).map_err(|e| anyhow::anyhow!(e))?;
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let response = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => handle_and_save(store, &mut storage, request.method(), request.url(), &body),
+            Err(e) => ApiError::Internal(e.into()).into(),
+        };
+
+        let result = match response.body {
+            Some(body) => request.respond(
+                Response::from_string(body.to_string())
+                    .with_status_code(response.status)
+                    .with_header(content_type.clone()),
+            ),
+            None => request.respond(Response::empty(response.status)),
+        };
+        if let Err(e) = result {
+            eprintln!("Error sending response: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Routes a single request and saves the storage when the request modified it.
+/// When it cannot be saved, the request fails with an internal error and the
+/// storage is loaded again, so that it keeps matching the data directory.
+pub fn handle_and_save(
+    store: &JsonFileStore,
+    storage: &mut ObjectStorage,
+    method: &Method,
+    url: &str,
+    body: &str,
+) -> ApiResponse {
+    let response = handle(storage, method, url, body);
+    let modifies_storage = !matches!(method, Method::Get | Method::Head);
+    if modifies_storage && response.status < 300 {
+        if let Err(e) = save(store, storage) {
+            return ApiError::Internal(e).into();
+        }
+    }
+    response
+}
+
+/// Saves `storage`, reloading it from `store` when it could not be saved.
+fn save(store: &JsonFileStore, storage: &mut ObjectStorage) -> anyhow::Result<()> {
+    let error = match store.save(storage) {
+        Ok(()) => return Ok(()),
+        Err(e) => e.context(format!(
+            "Error saving data to {}",
+            store.data_dir().display()
+        )),
+    };
+    match store.load() {
+        Ok(saved) => *storage = saved,
+        Err(e) => eprintln!("Error reloading data: {:#}", e),
+    }
+    Err(error)
+}
+
+/// Routes a single request to the matching handler.
+pub fn handle(storage: &mut ObjectStorage, method: &Method, url: &str, body: &str) -> ApiResponse {
+    route(storage, method, url, body).unwrap_or_else(ApiResponse::from)
+}
+
+fn route(
+    storage: &mut ObjectStorage,
+    method: &Method,
+    url: &str,
+    body: &str,
+) -> Result<ApiResponse, ApiError> {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match (method, segments.as_slice()) {
+        (Method::Get, ["users"]) => ApiResponse::ok(storage.users()),
+        (Method::Post, ["users"]) => create_user(storage, body),
+        (Method::Get, ["users", id]) => ApiResponse::ok(find(storage.get_user(id), "User", id)?),
+        (Method::Put, ["users", id]) => update_user(storage, id, body),
+        (Method::Delete, ["users", id]) => delete_user(storage, id),
+        (Method::Get, ["rental_spaces"]) => ApiResponse::ok(storage.rental_spaces()),
+        (Method::Post, ["rental_spaces"]) => create_rental_space(storage, body),
+        (Method::Get, ["rental_spaces", id]) => {
+            ApiResponse::ok(find(storage.get_rental_space(id), "Rental space", id)?)
+        }
+        (Method::Put, ["rental_spaces", id]) => update_rental_space(storage, id, body),
+        (Method::Delete, ["rental_spaces", id]) => delete_rental_space(storage, id),
+        (Method::Get, ["contracts"]) => ApiResponse::ok(storage.contracts()),
+        (Method::Get, ["contracts", id]) => {
+            ApiResponse::ok(find(storage.get_contract(id), "Contract", id)?)
+        }
+        (Method::Post, ["contracts", id, "transitions"]) => transition_contract(storage, id, body),
+        (Method::Post, ["matchings"]) => create_matchings(storage, body),
+        (Method::Get, ["waitlist"]) => ApiResponse::ok(storage.waitlist_positions()),
+        (Method::Get, ["waitlist", user_id]) => ApiResponse::ok(find(
+            storage.get_waitlist_position(user_id).as_ref(),
+            "Waitlisted user",
+            user_id,
+        )?),
+        (_, ["users"] | ["users", _] | ["rental_spaces"] | ["rental_spaces", _])
+        | (_, ["contracts"] | ["contracts", _] | ["contracts", _, "transitions"] | ["matchings"])
+        | (_, ["waitlist"] | ["waitlist", _]) => Err(ApiError::MethodNotAllowed),
+        _ => Err(ApiError::NotFound(format!("No route for {}", path))),
+    }
+}
+
+fn create_user(storage: &mut ObjectStorage, body: &str) -> Result<ApiResponse, ApiError> {
+    let request: AddUserRequest = parse_body(body)?;
+    ApiResponse::created(storage.create_user(request)?)
+}
+
+fn update_user(storage: &mut ObjectStorage, id: &str, body: &str) -> Result<ApiResponse, ApiError> {
+    find(storage.get_user(id), "User", id)?;
+    let request: AddUserRequest = parse_body(body)?;
+    ApiResponse::ok(storage.update_user(id, request)?)
+}
+
+fn delete_user(storage: &mut ObjectStorage, id: &str) -> Result<ApiResponse, ApiError> {
+    let user = find(storage.get_user(id), "User", id)?;
+    if storage
+        .rental_spaces()
+        .iter()
+        .any(|rental_space| rental_space.owner_id() == user.id())
+    {
+        return Err(ApiError::Conflict(format!(
+            "User with id {} still owns rental spaces",
+            id
+        )));
+    }
+    storage.remove_user(id);
+    ApiResponse::no_content()
+}
+
+fn create_rental_space(storage: &mut ObjectStorage, body: &str) -> Result<ApiResponse, ApiError> {
+    let request: AddRentalSpaceRequest = parse_body(body)?;
+    ApiResponse::created(storage.create_rental_space(request)?)
+}
+
+fn update_rental_space(
+    storage: &mut ObjectStorage,
+    id: &str,
+    body: &str,
+) -> Result<ApiResponse, ApiError> {
+    find(storage.get_rental_space(id), "Rental space", id)?;
+    let request: AddRentalSpaceRequest = parse_body(body)?;
+    ApiResponse::ok(storage.update_rental_space(id, request)?)
+}
+
+fn delete_rental_space(storage: &mut ObjectStorage, id: &str) -> Result<ApiResponse, ApiError> {
+    let rental_space = find(storage.get_rental_space(id), "Rental space", id)?;
+    if storage
+        .contracts()
+        .iter()
+        .any(|contract| contract.rental_space_id() == rental_space.id())
+    {
+        return Err(ApiError::Conflict(format!(
+            "Rental space with id {} is still referenced by contracts",
+            id
+        )));
+    }
+    storage.remove_rental_space(id);
+    ApiResponse::no_content()
+}
+
+fn transition_contract(
+    storage: &mut ObjectStorage,
+    id: &str,
+    body: &str,
+) -> Result<ApiResponse, ApiError> {
+    find(storage.get_contract(id), "Contract", id)?;
+    let action: ContractAction = parse_body(body)?;
+    ApiResponse::ok(storage.transition_contract(id, action)?)
+}
+
+fn create_matchings(storage: &mut ObjectStorage, body: &str) -> Result<ApiResponse, ApiError> {
+    let request: MatchingRequest = if body.trim().is_empty() {
+        MatchingRequest::default()
+    } else {
+        parse_body(body)?
+    };
+
+    let horizon = request.horizon.unwrap_or_default();
+    horizon.validate().map_err(ApiError::Validation)?;
+    let mut matching_engine = MatchingEngine::new(storage)
+        .with_horizon(horizon)
+        .with_ordering(request.ordering)
+        .with_incremental(request.incremental)
+        .with_max_nodes(MAX_NODES);
+    if request.blocking_pairs {
+        matching_engine =
+            matching_engine.with_stability_check(StableMatching::new(request.weights));
+    }
+    if let Some(renewal) = request.renewal {
+        renewal.validate().map_err(ApiError::Validation)?;
+        matching_engine = matching_engine.with_renewal(renewal);
+    }
+    if let Some(seed) = request.seed {
+        let now = horizon.start_date.and_time(NaiveTime::MIN).and_utc();
+        matching_engine = matching_engine.with_generator(Generator::deterministic(seed, now));
+    }
+    let matchings =
+        matching_engine.get_matchings(request.algorithm, request.with_subsplit, request.weights);
+
+    let status = if request.incremental { 201 } else { 200 };
+    let response = if request.report {
+        ApiResponse::with_status(
+            status,
+            MatchingsWithReport {
+                matchings: &matchings,
+                report: MatchingReport::new(storage, &matchings),
+            },
+        )
+    } else {
+        ApiResponse::with_status(status, &matchings)
+    };
+
+    if request.incremental {
+        storage.store_matchings(&matchings);
+    }
+    response
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(ApiError::InvalidJson)
+}
+
+fn find<'a, T>(object: Option<&'a T>, kind: &'static str, id: &str) -> Result<&'a T, ApiError> {
+    object.ok_or_else(|| {
+        ApiError::NotFound(
+            ObjectNotFound {
+                kind,
+                id: id.to_owned(),
+            }
+            .to_string(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json, std::fs};
+
+    fn post(storage: &mut ObjectStorage, url: &str, body: Value) -> ApiResponse {
+        handle(storage, &Method::Post, url, &body.to_string())
+    }
+
+    fn created_id(response: ApiResponse) -> String {
+        assert_eq!(response.status, 201);
+        response.body.unwrap()["id"].as_str().unwrap().to_owned()
+    }
+
+    fn user(storage: &mut ObjectStorage) -> String {
+        created_id(post(
+            storage,
+            "/users",
+            json!({"first_name": "Jane", "last_name": "Doe"}),
+        ))
+    }
+
+    fn guest(storage: &mut ObjectStorage) -> String {
+        created_id(post(
+            storage,
+            "/users",
+            json!({
+                "first_name": "John",
+                "last_name": "Doe",
+                "workspace_request": {"nb_workstations": 40, "budget": 40000},
+            }),
+        ))
+    }
+
+    fn rental_space(storage: &mut ObjectStorage, owner_id: &str) -> String {
+        created_id(post(
+            storage,
+            "/rental_spaces",
+            json!({
+                "name": "Rental Space",
+                "address": {
+                    "street": "123 Main St",
+                    "postal_code": "10001",
+                    "city": "Cityville",
+                    "country": "Country",
+                },
+                "surface": 10000,
+                "nb_workstations": 40,
+                "price_per_workstation": 400,
+                "owner_id": owner_id,
+            }),
+        ))
+    }
+
+    fn temp_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("office_space_split-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn reports_errors_with_their_status() {
+        let mut storage = ObjectStorage::new();
+
+        let response = post(&mut storage, "/users", json!({"first_name": ""}));
+        assert_eq!(response.status, 400);
+        let response = post(
+            &mut storage,
+            "/users",
+            json!({
+                "first_name": "John",
+                "last_name": "Doe",
+                "workspace_request": {"nb_workstations": 40, "budget": 40000, "term_months": 0},
+            }),
+        );
+        assert_eq!(response.status, 422);
+        assert_eq!(response.body.unwrap()["error"], "validation_failed");
+        let response = post(
+            &mut storage,
+            "/rental_spaces",
+            json!({
+                "name": "Rental Space",
+                "address": {
+                    "street": "123 Main St",
+                    "postal_code": "10001",
+                    "city": "Cityville",
+                    "country": "Country",
+                },
+                "surface": 10000,
+                "nb_workstations": 40,
+                "price_per_workstation": 400,
+                "owner_id": "usr-unknown",
+            }),
+        );
+        assert_eq!(response.status, 422);
+        assert_eq!(response.body.unwrap()["error"], "unknown_reference");
+        let response = handle(&mut storage, &Method::Get, "/users/usr-unknown", "");
+        assert_eq!(response.status, 404);
+        let response = handle(&mut storage, &Method::Patch, "/users", "");
+        assert_eq!(response.status, 405);
+        let response = post(
+            &mut storage,
+            "/matchings",
+            json!({"horizon": {"start_date": "2025-01-01", "nb_periods": 0}}),
+        );
+        assert_eq!(response.status, 422);
+    }
+
+    #[test]
+    fn keeps_owners_and_rental_spaces_referenced_elsewhere() {
+        let mut storage = ObjectStorage::new();
+        let host_id = user(&mut storage);
+        let rental_space_id = rental_space(&mut storage, &host_id);
+        guest(&mut storage);
+        let response = post(
+            &mut storage,
+            "/matchings",
+            json!({
+                "horizon": {"start_date": "2025-01-01", "nb_periods": 1},
+                "incremental": true,
+            }),
+        );
+        assert_eq!(response.status, 201);
+        assert_eq!(storage.contracts().len(), 1);
+
+        let url = format!("/rental_spaces/{}", rental_space_id);
+        let response = handle(&mut storage, &Method::Delete, &url, "");
+        assert_eq!(response.status, 409);
+        assert!(storage.get_rental_space(&rental_space_id).is_some());
+        let url = format!("/users/{}", host_id);
+        let response = handle(&mut storage, &Method::Delete, &url, "");
+        assert_eq!(response.status, 409);
+
+        let other_id = rental_space(&mut storage, &host_id);
+        let url = format!("/rental_spaces/{}", other_id);
+        let response = handle(&mut storage, &Method::Delete, &url, "");
+        assert_eq!(response.status, 204);
+    }
+
+    #[test]
+    fn only_stores_incremental_matchings() {
+        let mut storage = ObjectStorage::new();
+        let host_id = user(&mut storage);
+        rental_space(&mut storage, &host_id);
+        guest(&mut storage);
+        let horizon = json!({"start_date": "2025-01-01", "nb_periods": 1});
+
+        let response = post(&mut storage, "/matchings", json!({"horizon": horizon}));
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body.unwrap()["periods"][0]["contracts"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(storage.contracts().is_empty());
+
+        for _ in 0..2 {
+            let response = post(
+                &mut storage,
+                "/matchings",
+                json!({"horizon": horizon, "with_subsplit": true, "incremental": true}),
+            );
+            assert_eq!(response.status, 201);
+        }
+        assert_eq!(storage.contracts().len(), 1);
+
+        let response = post(&mut storage, "/matchings", json!({"horizon": horizon}));
+        assert_eq!(response.status, 200);
+        assert_eq!(storage.contracts().len(), 1);
+    }
+
+    #[test]
+    fn fails_when_the_storage_cannot_be_saved() {
+        let data_dir = temp_dir();
+        // The users are written to this file before it is renamed.
+        fs::create_dir_all(data_dir.join(".users.json.tmp")).unwrap();
+        let store = JsonFileStore::new(&data_dir);
+        let mut storage = store.load().unwrap();
+
+        let body = json!({"first_name": "Jane", "last_name": "Doe"}).to_string();
+        let response = handle_and_save(&store, &mut storage, &Method::Post, "/users", &body);
+        fs::remove_dir_all(&data_dir).unwrap();
+
+        assert_eq!(response.status, 500);
+        assert!(storage.users().is_empty());
+    }
+}
@@ -1,25 +1,172 @@
+pub mod amenity;
+pub mod cli;
 pub mod contract;
+pub mod http_api;
+pub mod location;
 pub mod matching;
 pub mod object_storage;
+pub mod partitioning;
+pub mod persistence;
+pub mod pricing;
 pub mod rental_space;
+pub mod report;
 pub mod user;
 pub mod user_interface;
+pub mod waitlist;
 
 use {
     chrono::{DateTime, Utc},
-    std::fmt,
-    uuid::Uuid,
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt,
+        sync::{Arc, Mutex},
+    },
+    uuid::{Builder, Uuid},
 };
 
-pub trait PrefixedUuid {
+pub trait PrefixedUuid: Sized {
     const PREFIX: &'static str;
 
-    fn generate() -> String {
-        format!("{}-{}", Self::PREFIX, Uuid::new_v4())
+    fn from_value(value: String) -> Self;
+
+    fn generate(generator: &Generator) -> Self {
+        Self::from_value(format!("{}-{}", Self::PREFIX, generator.uuid()))
     }
 }
 
+pub trait IdGenerator: Send + Sync {
+    fn uuid(&self) -> Uuid;
+
+    /// Returns an independent generator for the part of the work identified
+    /// by `key`, which is reproducible whenever this generator is.
+    fn fork(&self, key: &str) -> Arc<dyn IdGenerator>;
+}
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Generates random v4 UUIDs.
+pub struct RandomIdGenerator;
+
+/// Generates the same sequence of UUIDs for a given seed.
+pub struct SeededIdGenerator {
+    seed: u64,
+    rng: Mutex<StdRng>,
+}
+
+pub struct SystemClock;
+
+/// Always returns the same instant.
+pub struct FixedClock {
+    now: DateTime<Utc>,
+}
+
+/// Source of the identifiers and timestamps of all created objects. The
+/// default generator uses random identifiers and the system clock, while
+/// [`Generator::deterministic`] makes every run over the same data produce
+/// the same objects.
 #[derive(Clone)]
+pub struct Generator {
+    ids: Arc<dyn IdGenerator>,
+    clock: Arc<dyn Clock>,
+}
+
+impl IdGenerator for RandomIdGenerator {
+    fn uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+
+    fn fork(&self, _key: &str) -> Arc<dyn IdGenerator> {
+        Arc::new(RandomIdGenerator)
+    }
+}
+
+impl SeededIdGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl IdGenerator for SeededIdGenerator {
+    fn uuid(&self) -> Uuid {
+        Builder::from_random_bytes(self.rng.lock().unwrap().gen()).into_uuid()
+    }
+
+    /// The forked generator is seeded with the FNV-1a hash of the key, mixed
+    /// with the seed of this generator.
+    fn fork(&self, key: &str) -> Arc<dyn IdGenerator> {
+        let hash = key
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64 ^ self.seed, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            });
+        Arc::new(SeededIdGenerator::new(hash))
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+}
+
+impl Generator {
+    pub fn new(ids: Arc<dyn IdGenerator>, clock: Arc<dyn Clock>) -> Self {
+        Self { ids, clock }
+    }
+
+    pub fn deterministic(seed: u64, now: DateTime<Utc>) -> Self {
+        Self::new(
+            Arc::new(SeededIdGenerator::new(seed)),
+            Arc::new(FixedClock::new(now)),
+        )
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.ids.uuid()
+    }
+
+    /// Returns an independent generator sharing the clock of this one, see
+    /// [`IdGenerator::fork`].
+    pub fn fork(&self, key: &str) -> Generator {
+        Self::new(self.ids.fork(key), self.clock.clone())
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Returns a random number generator seeded from the next identifier, so
+    /// that it is reproducible whenever the identifiers are.
+    pub fn rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.uuid().as_u64_pair().0)
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new(Arc::new(RandomIdGenerator), Arc::new(SystemClock))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BaseFields<Id: PrefixedUuid> {
     id: Id,
     created_at: DateTime<Utc>,
@@ -36,10 +183,35 @@ impl<Id: PrefixedUuid + fmt::Debug> fmt::Debug for BaseFields<Id> {
 }
 
 impl<Id: PrefixedUuid> BaseFields<Id> {
-    pub fn new(id: Id) -> Self {
+    pub fn new(generator: &Generator) -> Self {
         Self {
-            id,
-            created_at: Utc::now(),
+            id: Id::generate(generator),
+            created_at: generator.now(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono::TimeZone};
+
+    fn ids(generator: &Generator) -> Vec<Uuid> {
+        (0..3).map(|_| generator.uuid()).collect()
+    }
+
+    #[test]
+    fn seeded_generators_repeat_their_ids() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let generator = Generator::deterministic(1, now);
+
+        assert_eq!(ids(&generator), ids(&Generator::deterministic(1, now)));
+        assert_ne!(ids(&generator), ids(&Generator::deterministic(2, now)));
+        assert_eq!(generator.now(), now);
+
+        let generator = Generator::deterministic(1, now);
+        let paris = ids(&generator.fork("paris"));
+        assert_eq!(paris, ids(&generator.fork("paris")));
+        assert_ne!(paris, ids(&generator.fork("lyon")));
+        assert_ne!(paris, ids(&Generator::deterministic(2, now).fork("paris")));
+    }
+}
@@ -0,0 +1,95 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::fmt,
+    validator::Validate,
+};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct Address {
+    #[validate(length(min = 1))]
+    pub street: String,
+    #[validate(length(min = 1, max = 16))]
+    pub postal_code: String,
+    #[validate(length(min = 1))]
+    pub city: String,
+    #[validate(length(min = 1))]
+    pub country: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub coordinates: Option<Coordinates>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Validate)]
+pub struct Coordinates {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub latitude: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub longitude: f64,
+}
+
+/// Where a guest is willing to rent a workspace. A rental space must satisfy
+/// every constraint that is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct LocationPreference {
+    /// Cities the rental space must be in, compared case-insensitively. Any
+    /// city is accepted if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cities: Vec<String>,
+    /// Area the rental space must be in. Rental spaces without coordinates
+    /// are not accepted if set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub within: Option<Area>,
+}
+
+/// Disk around a point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Validate)]
+pub struct Area {
+    #[validate(nested)]
+    pub center: Coordinates,
+    #[validate(range(min = 0.0))]
+    pub radius_km: f64,
+}
+
+impl Coordinates {
+    /// Returns the great-circle distance to `other` in kilometers.
+    pub fn distance_km(&self, other: &Coordinates) -> f64 {
+        let (latitude1, latitude2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let delta_latitude = latitude2 - latitude1;
+        let delta_longitude = (other.longitude - self.longitude).to_radians();
+
+        let a = (delta_latitude / 2.0).sin().powi(2)
+            + latitude1.cos() * latitude2.cos() * (delta_longitude / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+impl LocationPreference {
+    pub fn accepts(&self, address: &Address) -> bool {
+        let in_city = self.cities.is_empty()
+            || self
+                .cities
+                .iter()
+                .any(|city| city.trim().eq_ignore_ascii_case(address.city.trim()));
+
+        let in_area = self.within.is_none_or(|area| {
+            address
+                .coordinates
+                .is_some_and(|coordinates| coordinates.distance_km(&area.center) <= area.radius_km)
+        });
+
+        in_city && in_area
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, {} {}, {}",
+            self.street, self.postal_code, self.city, self.country
+        )
+    }
+}
@@ -1,9 +1,43 @@
-use office_space_split::{object_storage::ObjectStorage, user_interface::Interface};
+use {
+    clap::Parser,
+    office_space_split::{
+        cli::{self, Cli},
+        persistence::JsonFileStore,
+        user_interface::Interface,
+    },
+    std::process::ExitCode,
+};
 
-fn main() {
-    let mut storage = ObjectStorage::new();
-    let mut interface = Interface::new(&mut storage);
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let store = JsonFileStore::new(cli.data_dir);
+
+    match cli.command {
+        Some(command) => cli::run(command, &store),
+        None => run_interactive(&store),
+    }
+}
+
+fn run_interactive(store: &JsonFileStore) -> ExitCode {
+    let mut storage = match store.load() {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!(
+                "Error loading data from {}: {:#}",
+                store.data_dir().display(),
+                e
+            );
+            return ExitCode::from(cli::EXIT_FAILURE);
+        }
+    };
     loop {
-        interface.inquire_command();
+        Interface::new(&mut storage).inquire_command();
+        if let Err(e) = store.save(&storage) {
+            println!(
+                "Error saving data to {}: {:#}",
+                store.data_dir().display(),
+                e
+            );
+        }
     }
 }
@@ -1,235 +1,1044 @@
-use std::collections::{HashMap, HashSet};
+use {
+    chrono::{Months, NaiveDate, Utc},
+    clap::ValueEnum,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+        fmt,
+        sync::{
+            atomic::{self, AtomicUsize},
+            Mutex,
+        },
+        thread,
+    },
+    validator::{Validate, ValidationError},
+};
 
-use crate::{contract::Contract, object_storage::ObjectStorage, rental_space::Split, user::User};
+use crate::{
+    contract::{Contract, ContractId},
+    object_storage::ObjectStorage,
+    pricing::Currency,
+    rental_space::{RentalSpace, RentalSpaceId, Split},
+    user::{User, UserId, MAX_TERM_MONTHS},
+    Generator,
+};
 
-#[derive(Debug)]
+mod assignment;
+mod greedy;
+mod index;
+mod optimal;
+mod ordering;
+mod packing;
+mod reasons;
+mod renewal;
+mod scoring;
+mod stable;
+mod strategy;
+
+pub use {
+    assignment::Assignment,
+    greedy::{GreedyWithSplit, GreedyWithoutSplit},
+    optimal::{Objective, Optimal, DEFAULT_MAX_NODES},
+    ordering::OrderingPolicy,
+    packing::Packing,
+    renewal::RenewalPolicy,
+    scoring::{Scoring, ScoringWeights},
+    stable::{BlockingPair, StableMatching},
+    strategy::{Diagnostic, MatchingStrategy, PeriodInput, PeriodOutput},
+};
+
+use renewal::Tenancy;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Matchings {
-    pub year_1_contracts: Vec<Contract>,
-    pub year_2_contracts: Vec<Contract>,
+    pub periods: Vec<Period>,
     pub percentage_of_matched_users: i32,
+    pub unmatched_users: Vec<UnmatchedUser>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnmatchedUser {
+    pub user_id: UserId,
+    pub reason: UnmatchedReason,
+}
+
+/// Why a user could not be matched, from the most to the least fundamental
+/// reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmatchedReason {
+    /// The desired start date is after the start of the last period.
+    StartDateAfterHorizon,
+    /// No rental space is in the region requested by the user, or without a
+    /// region when the user requested none.
+    NoOfficeInRegion,
+    /// No rental space is in the location preferred by the user.
+    NoOfficeInLocation,
+    /// No rental space in the location preferred by the user is priced in the
+    /// currency of their budget.
+    NoOfficeInCurrency,
+    /// No rental space in the location preferred by the user has all the
+    /// amenities they require.
+    MissingAmenities,
+    /// No rental space has enough workstations.
+    NoOfficeBigEnough,
+    /// The requested workstations are too expensive in every rental space
+    /// that is big enough.
+    OverBudget,
+    /// The requested workstations would be affordable, but every split that
+    /// respects the split constraints is too expensive.
+    SplitConstraintViolated,
+    /// A suitable rental space exists, but it was leased to other users.
+    OfficeTakenEarlier,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Period {
+    pub start_date: NaiveDate,
+    pub contracts: Vec<Contract>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    /// Blocking pairs of the contracts generated by the strategy, if checked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocking_pairs: Vec<BlockingPair>,
+}
+
+/// Largest number of periods of a [`MatchingHorizon`].
+pub const MAX_NB_PERIODS: u32 = 1000;
+/// Largest length of a period of a [`MatchingHorizon`], in months.
+pub const MAX_PERIOD_MONTHS: u32 = 1200;
+
+/// Matching is run at the start of every period. Contracts start on that
+/// date and last for the term requested by the guest, so the workstations
+/// they lease become available again in the first period after their end.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_horizon_end"))]
+pub struct MatchingHorizon {
+    pub start_date: NaiveDate,
+    #[validate(range(min = 1, max = MAX_NB_PERIODS))]
+    pub nb_periods: u32,
+    #[serde(default = "default_period_months")]
+    #[validate(range(min = 1, max = MAX_PERIOD_MONTHS))]
+    pub period_months: u32,
+}
+
+fn default_period_months() -> u32 {
+    12
+}
+
+fn validate_horizon_end(horizon: &MatchingHorizon) -> Result<(), ValidationError> {
+    if horizon
+        .period_start_date(horizon.nb_periods.saturating_sub(1))
+        .is_none()
+    {
+        return Err(ValidationError::new("horizon_end").with_message(
+            "The contracts of the last period would end after the latest supported date".into(),
+        ));
+    }
+    Ok(())
+}
+
+impl MatchingHorizon {
+    pub fn new(start_date: NaiveDate, nb_periods: u32) -> Self {
+        Self {
+            start_date,
+            nb_periods,
+            period_months: default_period_months(),
+        }
+    }
+
+    pub fn with_period_months(mut self, period_months: u32) -> Self {
+        self.period_months = period_months;
+        self
+    }
+
+    /// Returns the start dates of the periods. Periods whose contracts could
+    /// end after the latest supported date, which only exist in horizons
+    /// failing validation, are left out.
+    pub fn period_start_dates(&self) -> Vec<NaiveDate> {
+        (0..self.nb_periods)
+            .map_while(|i| self.period_start_date(i))
+            .collect()
+    }
+
+    /// Returns the start date of the period at `index`, if the contracts
+    /// starting on that date end before the latest supported date whatever
+    /// their term.
+    fn period_start_date(&self, index: u32) -> Option<NaiveDate> {
+        let months = index.checked_mul(self.period_months)?;
+        let start_date = self.start_date.checked_add_months(Months::new(months))?;
+        start_date
+            .checked_add_months(Months::new(MAX_TERM_MONTHS))
+            .map(|_| start_date)
+    }
 }
 
+impl Default for MatchingHorizon {
+    fn default() -> Self {
+        Self::new(Utc::now().date_naive(), 2)
+    }
+}
+
+impl Matchings {
+    pub fn contracts(&self) -> impl Iterator<Item = &Contract> {
+        self.periods
+            .iter()
+            .flat_map(|period| period.contracts.iter())
+    }
+}
+
+/// Matching algorithm, chosen by the front ends and run with
+/// [`MatchingEngine::get_matchings`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    #[default]
+    Greedy,
+    OptimalUsers,
+    OptimalWorkstations,
+    /// Pack several guests into every rental space, always splitting
+    Packing,
+    /// Solve the assignment of guests to entire rental spaces, never splitting
+    Assignment,
+    /// Offer every guest the rental space ranking best by distance, price,
+    /// wasted workstations and missing amenities
+    Scoring,
+    /// Find a stable matching between guests ranking rental spaces as when
+    /// scoring and hosts ranking guests by budget, term and priority, never
+    /// splitting
+    Stable,
+}
+
+impl Algorithm {
+    /// Returns whether the algorithm considers guests in the order of the
+    /// [`OrderingPolicy`].
+    pub fn uses_ordering(&self) -> bool {
+        matches!(
+            self,
+            Algorithm::Greedy | Algorithm::Packing | Algorithm::Scoring
+        )
+    }
+
+    /// Returns whether the algorithm ranks splits with [`ScoringWeights`].
+    pub fn uses_weights(&self) -> bool {
+        matches!(self, Algorithm::Scoring | Algorithm::Stable)
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let algorithm = match self {
+            Algorithm::Greedy => "greedy",
+            Algorithm::OptimalUsers => "optimal (users)",
+            Algorithm::OptimalWorkstations => "optimal (workstations)",
+            Algorithm::Packing => "packing",
+            Algorithm::Assignment => "assignment",
+            Algorithm::Scoring => "scoring",
+            Algorithm::Stable => "stable",
+        };
+        write!(f, "{}", algorithm)
+    }
+}
+
+/// Users and rental spaces are considered in the order of
+/// [`ObjectStorage::users`] and [`ObjectStorage::rental_spaces`], so that two
+/// runs over the same data with a deterministic [`Generator`] produce the same
+/// contracts.
 pub struct MatchingEngine<'a> {
     storage: &'a ObjectStorage,
+    horizon: MatchingHorizon,
+    ordering: OrderingPolicy,
+    generator: Generator,
+    nb_threads: usize,
+    incremental: bool,
+    renewal: Option<RenewalPolicy>,
+    waitlist: bool,
+    stability_check: Option<StableMatching>,
+    max_nodes: u64,
 }
 
 impl<'a> MatchingEngine<'a> {
     pub fn new(storage: &'a ObjectStorage) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            horizon: MatchingHorizon::default(),
+            ordering: OrderingPolicy::default(),
+            generator: storage.generator().clone(),
+            nb_threads: thread::available_parallelism().map_or(1, usize::from),
+            incremental: false,
+            renewal: None,
+            waitlist: false,
+            stability_check: None,
+            max_nodes: DEFAULT_MAX_NODES,
+        }
     }
 
-    pub fn get_greedy_matchings(&self, with_subsplit: bool) -> Matchings {
-        let mut year_1_contracts: Vec<Contract> = Vec::new();
-        let mut year_2_contracts: Vec<Contract> = Vec::new();
+    pub fn with_horizon(mut self, horizon: MatchingHorizon) -> Self {
+        self.horizon = horizon;
+        self
+    }
 
-        let users_with_workspace_request: Vec<&User> = self
-            .storage
-            .users()
-            .into_iter()
-            .filter(|user| user.workspace_request.is_some())
-            .collect();
+    /// Sets the order in which the greedy matching considers guests.
+    pub fn with_ordering(mut self, ordering: OrderingPolicy) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Uses `generator` for the identifiers and timestamps of the generated
+    /// splits and contracts.
+    pub fn with_generator(mut self, generator: Generator) -> Self {
+        self.generator = generator;
+        self
+    }
 
-        let nb_users_with_workspace_request = users_with_workspace_request.len();
+    /// Sets the number of markets matched concurrently, the number of
+    /// available CPUs by default.
+    pub fn with_threads(mut self, nb_threads: usize) -> Self {
+        self.nb_threads = nb_threads;
+        self
+    }
 
-        let original_splits: HashMap<String, Split> = self
-            .storage
-            .rental_spaces()
+    /// Keeps the contracts of the storage that are proposed, accepted or
+    /// active and have not ended before the horizon starts: their
+    /// workstations stay leased until their end date, and their guests are not
+    /// matched again. Only the remaining capacity is offered to the other
+    /// guests. Otherwise, every rental space is considered free, and the
+    /// generated contracts should not be stored alongside the existing ones.
+    pub fn with_incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Renews the contracts of the guests who asked for it when they end,
+    /// including the kept contracts in incremental mode. Otherwise, guests
+    /// leave when their contract ends.
+    pub fn with_renewal(mut self, renewal: RenewalPolicy) -> Self {
+        self.renewal = Some(renewal);
+        self
+    }
+
+    /// Only matches the guests of the [`Waitlist`](crate::waitlist::Waitlist),
+    /// in the order of the queue rather than the one set by the
+    /// [`OrderingPolicy`].
+    pub fn with_waitlist(mut self, waitlist: bool) -> Self {
+        self.waitlist = waitlist;
+        self
+    }
+
+    /// Lists the blocking pairs of the contracts generated in every period,
+    /// according to the preferences of `preferences`, so that any strategy
+    /// can be compared with the stable matching.
+    pub fn with_stability_check(mut self, preferences: StableMatching) -> Self {
+        self.stability_check = Some(preferences);
+        self
+    }
+
+    /// Sets the number of assignments the optimal algorithms explore in every
+    /// period, [`DEFAULT_MAX_NODES`] by default.
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    /// Runs `strategy` at the start of every period of the horizon. Guests
+    /// are offered to the strategy in the order set by the [`OrderingPolicy`],
+    /// which is drawn once for the whole horizon, and the guests left
+    /// unmatched are offered again in the following periods.
+    ///
+    /// Guests are only matched with rental spaces of the region they request,
+    /// so every region is an independent market. Markets are matched
+    /// concurrently, each with its own generator forked from the engine's
+    /// one, and their results are merged in the order of the regions, so that
+    /// the result does not depend on the number of threads.
+    pub fn run(&self, strategy: &dyn MatchingStrategy) -> Matchings {
+        let existing_contracts = self.existing_contracts();
+        let markets = self.markets(&existing_contracts);
+        let mut reserved = Occupancy::default();
+        reserved.lease(existing_contracts);
+
+        let results: Vec<MarketMatchings> = if self.nb_threads <= 1 || markets.len() <= 1 {
+            markets
+                .iter()
+                .map(|market| self.run_market(market, &reserved, strategy))
+                .collect()
+        } else {
+            let next_market = AtomicUsize::new(0);
+            let results: Vec<Mutex<Option<MarketMatchings>>> =
+                markets.iter().map(|_| Mutex::new(None)).collect();
+            thread::scope(|scope| {
+                for _ in 0..std::cmp::min(self.nb_threads, markets.len()) {
+                    scope.spawn(|| {
+                        while let Some(market) =
+                            markets.get(next_market.fetch_add(1, atomic::Ordering::Relaxed))
+                        {
+                            let index = market.index;
+                            *results[index].lock().unwrap() =
+                                Some(self.run_market(market, &reserved, strategy));
+                        }
+                    });
+                }
+            });
+            results
+                .into_iter()
+                .map(|result| result.into_inner().unwrap().unwrap())
+                .collect()
+        };
+
+        let mut periods: Vec<Period> = self
+            .horizon
+            .period_start_dates()
             .into_iter()
-            .map(|rental_space| {
-                let split: Split = rental_space.into();
-                return (split.id_value().to_owned(), split);
+            .map(|start_date| Period {
+                start_date,
+                contracts: Vec::new(),
+                diagnostics: Vec::new(),
+                blocking_pairs: Vec::new(),
             })
             .collect();
+        let mut unmatched_users: Vec<UnmatchedUser> = Vec::new();
+        let mut nb_users_with_workspace_request = 0;
+
+        for result in results {
+            for (period, market_period) in periods.iter_mut().zip(result.periods) {
+                period.contracts.extend(market_period.contracts);
+                period.diagnostics.extend(market_period.diagnostics);
+                period.blocking_pairs.extend(market_period.blocking_pairs);
+            }
+            unmatched_users.extend(result.unmatched_users);
+            nb_users_with_workspace_request += result.nb_users_with_workspace_request;
+        }
+
+        Matchings {
+            periods,
+            percentage_of_matched_users: percentage_of_matched_users(
+                unmatched_users.len(),
+                nb_users_with_workspace_request,
+            ),
+            unmatched_users,
+        }
+    }
+
+    fn run_market(
+        &self,
+        market: &Market,
+        reserved: &Occupancy,
+        strategy: &dyn MatchingStrategy,
+    ) -> MarketMatchings {
+        let generator = self.generator.fork(&market.key());
+
+        let mut periods: Vec<Period> = Vec::new();
+
+        let mut unmatched_users = market.users.clone();
+        if !self.waitlist {
+            self.ordering.sort(&mut unmatched_users, &generator);
+        }
+
+        let nb_users_with_workspace_request = unmatched_users.len();
+
+        let mut occupancy = reserved.clone();
 
-        let mut unmatched_users: HashSet<&str> = users_with_workspace_request
+        let guests: HashMap<&UserId, &User> =
+            market.users.iter().map(|&user| (user.id(), user)).collect();
+        let mut tenancies: Vec<Tenancy> = market
+            .tenancies
             .iter()
-            .map(|user| user.id_value())
+            .map(|&(contract, guest)| Tenancy {
+                contract: contract.clone(),
+                guest,
+            })
             .collect();
 
-        match with_subsplit {
-            false => {
-                let mut available_splits: HashSet<&str> =
-                    original_splits.keys().map(String::as_str).collect();
-
-                for user in users_with_workspace_request {
-                    Self::match_user_greedily_without_split(
-                        user,
-                        &original_splits,
-                        &mut available_splits,
-                        &mut unmatched_users,
-                        &mut year_1_contracts,
-                    );
-                }
+        for start_date in self.horizon.period_start_dates() {
+            let mut contracts = Vec::new();
+            let mut diagnostics = Vec::new();
+            let end_date = start_date
+                .checked_add_months(Months::new(self.horizon.period_months))
+                .unwrap_or(NaiveDate::MAX);
+            if let Some(renewal) = &self.renewal {
+                let renewed = renewal.renew(&mut tenancies, end_date, &mut occupancy, &generator);
+                contracts.extend(renewed.contracts);
+                diagnostics.extend(renewed.diagnostics);
+            }
+
+            let users: Vec<&User> = unmatched_users
+                .iter()
+                .copied()
+                .filter(|user| wants_to_start_by(user, start_date))
+                .collect();
+
+            let splits = available_splits(
+                &market.rental_spaces,
+                &occupancy,
+                start_date,
+                strategy.allows_subsplit(),
+                &generator,
+            );
 
-                let unmatched_users_after_year_1 = unmatched_users.clone();
-                let mut available_splits: HashSet<&str> =
-                    original_splits.keys().map(String::as_str).collect();
-
-                for user_id in unmatched_users_after_year_1 {
-                    let user = self.storage.get_user(user_id).unwrap();
-                    Self::match_user_greedily_without_split(
-                        user,
-                        &original_splits,
-                        &mut available_splits,
-                        &mut unmatched_users,
-                        &mut year_2_contracts,
-                    );
+            let offered = self
+                .stability_check
+                .map(|preferences| (preferences, users.clone(), splits.clone()));
+
+            let output = strategy.match_period(PeriodInput {
+                start_date,
+                users,
+                splits,
+                generator: &generator,
+            });
+
+            let blocking_pairs = match offered {
+                Some((preferences, users, splits)) => {
+                    preferences.blocking_pairs(&users, &splits, &output.contracts)
                 }
+                None => Vec::new(),
+            };
+
+            let matched_users: HashSet<&UserId> = output
+                .contracts
+                .iter()
+                .map(|contract| contract.guest_id())
+                .collect();
+            unmatched_users.retain(|user| !matched_users.contains(user.id()));
+
+            occupancy.lease(&output.contracts);
+            if self.renewal.is_some() {
+                tenancies.extend(output.contracts.iter().filter_map(|contract| {
+                    let guest = guests[contract.guest_id()];
+                    guest
+                        .workspace_request
+                        .as_ref()
+                        .is_some_and(|request| request.renew)
+                        .then(|| Tenancy {
+                            contract: contract.clone(),
+                            guest,
+                        })
+                }));
             }
-            true => {
-                let mut splits: HashMap<String, Split> = original_splits
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
-
-                let mut available_splits: HashSet<String> =
-                    original_splits.keys().map(String::to_owned).collect();
-
-                for user in users_with_workspace_request {
-                    Self::match_user_greedily_with_split(
-                        user,
-                        &mut splits,
-                        &mut available_splits,
-                        &mut unmatched_users,
-                        &mut year_1_contracts,
-                    );
-                }
+            contracts.extend(output.contracts);
+            diagnostics.extend(output.diagnostics);
 
-                let unmatched_users_after_year_1 = unmatched_users.clone();
+            // Contracts shorter than the period are renewed before it ends.
+            if let Some(renewal) = &self.renewal {
+                let renewed = renewal.renew(&mut tenancies, end_date, &mut occupancy, &generator);
+                contracts.extend(renewed.contracts);
+                diagnostics.extend(renewed.diagnostics);
+            }
 
-                let mut splits: HashMap<String, Split> = original_splits
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
-
-                let mut available_splits: HashSet<String> =
-                    original_splits.keys().map(String::to_owned).collect();
-
-                for user_id in unmatched_users_after_year_1 {
-                    let user = self.storage.get_user(user_id).unwrap();
-                    Self::match_user_greedily_with_split(
-                        user,
-                        &mut splits,
-                        &mut available_splits,
-                        &mut unmatched_users,
-                        &mut year_2_contracts,
-                    );
-                }
+            periods.push(Period {
+                start_date,
+                contracts,
+                diagnostics,
+                blocking_pairs,
+            });
+        }
+
+        MarketMatchings {
+            periods,
+            unmatched_users: self.unmatched_users(
+                market,
+                &unmatched_users,
+                strategy.allows_subsplit(),
+                &generator,
+            ),
+            nb_users_with_workspace_request,
+        }
+    }
+
+    /// Matches guests one at a time, see [`GreedyWithSplit`] and
+    /// [`GreedyWithoutSplit`].
+    pub fn get_greedy_matchings(&self, with_subsplit: bool) -> Matchings {
+        match with_subsplit {
+            false => self.run(&GreedyWithoutSplit),
+            true => self.run(&GreedyWithSplit),
+        }
+    }
+
+    /// Finds a matching that maximizes the objective, solving every period
+    /// exactly. As in the greedy mode, a period only considers the users left
+    /// unmatched in the previous periods, so both results can be compared
+    /// directly.
+    pub fn get_optimal_matchings(&self, with_subsplit: bool, objective: Objective) -> Matchings {
+        self.run(&Optimal::new(with_subsplit, objective).with_max_nodes(self.max_nodes))
+    }
+
+    /// Packs several guests into every split, see [`Packing`].
+    pub fn get_packed_matchings(&self) -> Matchings {
+        self.run(&Packing)
+    }
+
+    /// Offers every guest the split ranking best by distance, price, wasted
+    /// workstations and missing amenities, see [`Scoring`].
+    pub fn get_scored_matchings(&self, with_subsplit: bool, weights: ScoringWeights) -> Matchings {
+        self.run(&Scoring::new(with_subsplit).with_weights(weights))
+    }
+
+    /// Matches guests and entire splits so that no guest and host would both
+    /// rather be leased to each other, see [`StableMatching`].
+    pub fn get_stable_matchings(&self, weights: ScoringWeights) -> Matchings {
+        self.run(&StableMatching::new(weights))
+    }
+
+    /// Runs `algorithm`. Algorithms that never or always split ignore
+    /// `with_subsplit`, and only the scoring and stable ones use `weights`.
+    pub fn get_matchings(
+        &self,
+        algorithm: Algorithm,
+        with_subsplit: bool,
+        weights: ScoringWeights,
+    ) -> Matchings {
+        match algorithm {
+            Algorithm::Greedy => self.get_greedy_matchings(with_subsplit),
+            Algorithm::OptimalUsers => {
+                self.get_optimal_matchings(with_subsplit, Objective::MatchedUsers)
             }
+            Algorithm::OptimalWorkstations => {
+                self.get_optimal_matchings(with_subsplit, Objective::MatchedWorkstations)
+            }
+            Algorithm::Packing => self.get_packed_matchings(),
+            Algorithm::Assignment => self.run(&Assignment),
+            Algorithm::Scoring => self.get_scored_matchings(with_subsplit, weights),
+            Algorithm::Stable => self.get_stable_matchings(weights),
         }
-        Matchings {
-            year_1_contracts,
-            year_2_contracts,
-            percentage_of_matched_users: ((1.0
-                - (unmatched_users.len() as f32 / nb_users_with_workspace_request as f32))
-                * 100.0)
-                .round() as i32,
-        }
-    }
-
-    fn match_user_greedily_with_split(
-        user: &User,
-        splits: &mut HashMap<String, Split>,
-        available_splits: &mut HashSet<String>,
-        unmatched_users: &mut HashSet<&str>,
-        contracts: &mut Vec<Contract>,
-    ) {
-        let matched_rental_space_without_split = available_splits
+    }
+
+    /// Finds why every user left unmatched could not be matched, by checking
+    /// their request against every rental space of the market in their
+    /// preferred location as if it were entirely free.
+    fn unmatched_users(
+        &self,
+        market: &Market,
+        unmatched_users: &[&User],
+        with_subsplit: bool,
+        generator: &Generator,
+    ) -> Vec<UnmatchedUser> {
+        let last_period_start_date = self.horizon.period_start_dates().last().copied();
+        let splits: Vec<Split> = market
+            .rental_spaces
             .iter()
-            .filter(|&split_id| {
-                !splits
-                    .get(split_id)
-                    .unwrap()
-                    .can_be_subsplit(user.workspace_request.as_ref().unwrap().nb_workstations)
-            })
-            .filter(|&split_id| {
-                let split = splits.get(split_id).unwrap();
-                split.nb_workstations >= user.workspace_request.as_ref().unwrap().nb_workstations
-                    && split.price() <= user.workspace_request.as_ref().unwrap().budget
+            .map(|rental_space| rental_space.split(generator))
+            .collect();
+        let catalog = reasons::Catalog::new(splits.clone());
+        let currencies: BTreeSet<&Currency> = splits.iter().map(Split::currency).collect();
+
+        unmatched_users
+            .iter()
+            .map(|user| {
+                let request = user.workspace_request.as_ref().unwrap();
+                let reason = match last_period_start_date {
+                    Some(date) if wants_to_start_by(user, date) => {
+                        if market.rental_spaces.is_empty() {
+                            UnmatchedReason::NoOfficeInRegion
+                        } else if request.location.is_none()
+                            && request.required_amenities.is_empty()
+                            && currencies
+                                .iter()
+                                .all(|&currency| currency == &request.budget.currency)
+                        {
+                            catalog.reason(request, with_subsplit)
+                        } else {
+                            reasons::restricted_reason(request, &splits, with_subsplit)
+                        }
+                    }
+                    _ => UnmatchedReason::StartDateAfterHorizon,
+                };
+                UnmatchedUser {
+                    user_id: user.id().clone(),
+                    reason,
+                }
             })
-            .min_by_key(|&split_id| splits.get(split_id).unwrap().nb_workstations)
-            .map(String::to_owned);
-
-        if let Some(split_id) = matched_rental_space_without_split {
-            unmatched_users.remove(user.id_value());
-            available_splits.remove(&split_id);
-            let split = splits.get(&split_id).unwrap().clone();
-            contracts.push(Contract::new(
-                split.parent_office_id.clone(),
-                split.owner_id.clone(),
-                user.id().clone(),
-                split.nb_workstations,
-                split.price(),
-            ));
+            .collect()
+    }
+
+    /// Returns the stored contracts kept in incremental mode, leaving out the
+    /// ones that ended before the horizon starts, whose guests are matched
+    /// again.
+    fn existing_contracts(&self) -> Vec<&'a Contract> {
+        if !self.incremental {
+            return Vec::new();
+        }
+        self.storage
+            .contracts()
+            .into_iter()
+            .filter(|contract| contract.status().holds_workstations())
+            .filter(|contract| contract.end_date() >= self.horizon.start_date)
+            .collect()
+    }
+
+    /// Groups the users with a workspace request and the rental spaces by
+    /// region, leaving out the guests of `existing_contracts`, whose contracts
+    /// are grouped by region to be renewed if needed. Regions without any user
+    /// to match or contract to renew are left out.
+    fn markets(&self, existing_contracts: &[&'a Contract]) -> Vec<Market<'a>> {
+        let mut markets: BTreeMap<Option<&str>, Market> = BTreeMap::new();
+        let guest_ids: HashSet<&UserId> = existing_contracts
+            .iter()
+            .map(|contract| contract.guest_id())
+            .collect();
+
+        let users = if self.waitlist {
+            self.storage.waitlisted_users()
         } else {
-            let matched_rental_space_with_split = available_splits
-                .iter()
-                .filter(|&split_id| {
-                    splits
-                        .get(split_id)
-                        .unwrap()
-                        .can_be_subsplit(user.workspace_request.as_ref().unwrap().nb_workstations)
-                })
-                .filter(|&split_id| {
-                    let split = splits.get(split_id).unwrap();
-                    let nb_workstations_in_split = std::cmp::max(
-                        split.subsplit_min_nb_workstations().unwrap(),
-                        user.workspace_request.as_ref().unwrap().nb_workstations,
-                    );
-                    nb_workstations_in_split * split.price_per_workstation
-                        <= user.workspace_request.as_ref().unwrap().budget
-                })
-                .max_by_key(|&split_id| splits.get(split_id).unwrap().nb_workstations)
-                .map(String::to_owned);
-
-            if let Some(split_id) = matched_rental_space_with_split {
-                unmatched_users.remove(user.id_value());
-                let original_split = splits.remove(&split_id).unwrap();
-                let (split1, split2) = original_split
-                    .subsplit(user.workspace_request.as_ref().unwrap().nb_workstations)
-                    .unwrap();
-                available_splits.remove(&split_id);
-                available_splits.insert(split2.id_value().to_owned());
-                splits.insert(split2.id_value().to_owned(), split2);
-                contracts.push(Contract::new(
-                    split1.parent_office_id.clone(),
-                    split1.owner_id.clone(),
-                    user.id().clone(),
-                    split1.nb_workstations,
-                    split1.price(),
-                ));
+            self.storage.users()
+        };
+        for user in users {
+            if guest_ids.contains(user.id()) {
+                continue;
+            }
+            if let Some(request) = &user.workspace_request {
+                let region = request.region.as_deref();
+                markets
+                    .entry(region)
+                    .or_insert_with(|| Market::new(region))
+                    .users
+                    .push(user);
+            }
+        }
+
+        if self.renewal.is_some() {
+            let users: HashMap<&UserId, &User> = self
+                .storage
+                .users()
+                .into_iter()
+                .map(|user| (user.id(), user))
+                .collect();
+            // Only the last contract of every chain of renewals is renewed.
+            let renewed: HashSet<&ContractId> = self
+                .storage
+                .contracts()
+                .into_iter()
+                .filter_map(Contract::renewed_from)
+                .collect();
+            for &contract in existing_contracts {
+                if renewed.contains(contract.id()) {
+                    continue;
+                }
+                let Some(&guest) = users.get(contract.guest_id()) else {
+                    continue;
+                };
+                if let Some(request) = guest.workspace_request.as_ref().filter(|r| r.renew) {
+                    let region = request.region.as_deref();
+                    markets
+                        .entry(region)
+                        .or_insert_with(|| Market::new(region))
+                        .tenancies
+                        .push((contract, guest));
+                }
+            }
+        }
+
+        for rental_space in self.storage.rental_spaces() {
+            if let Some(market) = markets.get_mut(&rental_space.region()) {
+                market.rental_spaces.push(rental_space);
+            }
+        }
+
+        // Contracts of rental spaces removed since they were signed are not
+        // renewed.
+        for market in markets.values_mut() {
+            let rental_spaces = &market.rental_spaces;
+            market.tenancies.retain(|(contract, _)| {
+                rental_spaces
+                    .iter()
+                    .any(|rental_space| rental_space.id() == contract.rental_space_id())
+            });
+        }
+
+        markets
+            .into_values()
+            .enumerate()
+            .map(|(index, market)| Market { index, ..market })
+            .collect()
+    }
+}
+
+/// Guests and rental spaces of a region, which never interact with those of
+/// other regions.
+struct Market<'a> {
+    index: usize,
+    region: Option<&'a str>,
+    users: Vec<&'a User>,
+    rental_spaces: Vec<&'a RentalSpace>,
+    /// Kept contracts to renew, with their guest.
+    tenancies: Vec<(&'a Contract, &'a User)>,
+}
+
+struct MarketMatchings {
+    periods: Vec<Period>,
+    unmatched_users: Vec<UnmatchedUser>,
+    nb_users_with_workspace_request: usize,
+}
+
+impl<'a> Market<'a> {
+    fn new(region: Option<&'a str>) -> Self {
+        Self {
+            index: 0,
+            region,
+            users: Vec::new(),
+            rental_spaces: Vec::new(),
+            tenancies: Vec::new(),
+        }
+    }
+
+    fn key(&self) -> String {
+        match self.region {
+            Some(region) => format!("region:{}", region),
+            None => String::new(),
+        }
+    }
+}
+
+/// Returns the part of every rental space that is free on `date`. Without
+/// splitting, only rental spaces that are entirely free are returned.
+fn available_splits(
+    rental_spaces: &[&RentalSpace],
+    occupancy: &Occupancy,
+    date: NaiveDate,
+    with_subsplit: bool,
+    generator: &Generator,
+) -> Vec<Split> {
+    rental_spaces
+        .iter()
+        .filter_map(|rental_space| {
+            let nb_leased_workstations = occupancy.nb_leased_workstations(rental_space.id(), date);
+            if !with_subsplit && nb_leased_workstations > 0 {
+                return None;
             }
+            rental_space.free_split(nb_leased_workstations, generator)
+        })
+        .collect()
+}
+
+fn percentage_of_matched_users(nb_unmatched_users: usize, nb_users: usize) -> i32 {
+    ((1.0 - (nb_unmatched_users as f32 / nb_users as f32)) * 100.0).round() as i32
+}
+
+/// Workstations leased by the contracts kept or generated so far, per rental
+/// space.
+#[derive(Default, Clone)]
+struct Occupancy {
+    leases: HashMap<RentalSpaceId, Vec<(u32, NaiveDate)>>,
+}
+
+impl Occupancy {
+    fn lease<'c>(&mut self, contracts: impl IntoIterator<Item = &'c Contract>) {
+        for contract in contracts {
+            self.leases
+                .entry(contract.rental_space_id().clone())
+                .or_default()
+                .push((contract.nb_workstations(), contract.end_date()));
         }
     }
 
-    fn match_user_greedily_without_split(
-        user: &User,
-        splits: &HashMap<String, Split>,
-        available_splits: &mut HashSet<&str>,
-        unmatched_users: &mut HashSet<&str>,
-        contracts: &mut Vec<Contract>,
-    ) {
-        let matched_rental_space = available_splits
+    /// Keeps the workstations leased by `contract` until `end_date`, when it
+    /// is renewed.
+    fn extend(&mut self, contract: &Contract, end_date: NaiveDate) {
+        let leases = self
+            .leases
+            .entry(contract.rental_space_id().clone())
+            .or_default();
+        let lease = (contract.nb_workstations(), contract.end_date());
+        match leases.iter_mut().find(|other| **other == lease) {
+            Some(lease) => lease.1 = end_date,
+            None => leases.push((contract.nb_workstations(), end_date)),
+        }
+    }
+
+    fn nb_leased_workstations(&self, rental_space_id: &RentalSpaceId, date: NaiveDate) -> u32 {
+        self.leases
+            .get(rental_space_id)
+            .into_iter()
+            .flatten()
+            .filter(|(_, end_date)| date < *end_date)
+            .map(|(nb_workstations, _)| nb_workstations)
+            .sum()
+    }
+}
+
+fn wants_to_start_by(user: &User, date: NaiveDate) -> bool {
+    user.workspace_request
+        .as_ref()
+        .and_then(|request| request.desired_start_date)
+        .is_none_or(|desired_start_date| desired_start_date <= date)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{rental_space::AddRentalSpaceRequest, user::AddUserRequest},
+        chrono::NaiveTime,
+        serde_json::json,
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Adds a host with a rental space of 40 workstations in `region`.
+    fn add_rental_space(storage: &mut ObjectStorage, region: Option<&str>) {
+        let request: AddUserRequest =
+            serde_json::from_value(json!({"first_name": "Jane", "last_name": "Doe"})).unwrap();
+        let host_id = storage.create_user(request).unwrap().id_value().to_owned();
+        let request: AddRentalSpaceRequest = serde_json::from_value(json!({
+            "name": "Rental Space",
+            "address": {
+                "street": "123 Main St",
+                "postal_code": "10001",
+                "city": "Cityville",
+                "country": "Country",
+            },
+            "surface": 10000,
+            "nb_workstations": 40,
+            "price_per_workstation": 400,
+            "owner_id": host_id,
+            "region": region,
+        }))
+        .unwrap();
+        storage.create_rental_space(request).unwrap();
+    }
+
+    /// Adds a guest asking for 40 workstations for 12 months in `region`.
+    fn add_guest(storage: &mut ObjectStorage, region: Option<&str>) -> UserId {
+        let request: AddUserRequest = serde_json::from_value(json!({
+            "first_name": "John",
+            "last_name": "Doe",
+            "workspace_request": {"nb_workstations": 40, "budget": 40000, "region": region},
+        }))
+        .unwrap();
+        storage.create_user(request).unwrap().id().clone()
+    }
+
+    fn storage() -> ObjectStorage {
+        let mut storage = ObjectStorage::new();
+        add_rental_space(&mut storage, None);
+        add_guest(&mut storage, None);
+        storage
+    }
+
+    fn incremental_matchings(storage: &ObjectStorage, start_date: NaiveDate) -> Matchings {
+        MatchingEngine::new(storage)
+            .with_horizon(MatchingHorizon::new(start_date, 1))
+            .with_incremental(true)
+            .get_greedy_matchings(false)
+    }
+
+    #[test]
+    fn only_matches_guests_with_rental_spaces_of_their_region() {
+        let mut storage = ObjectStorage::new();
+        add_rental_space(&mut storage, Some("paris"));
+        let parisian = add_guest(&mut storage, Some("paris"));
+        let lyonnais = add_guest(&mut storage, Some("lyon"));
+        let anywhere = add_guest(&mut storage, None);
+
+        let matchings = MatchingEngine::new(&storage)
+            .with_horizon(MatchingHorizon::new(date(2025, 1, 1), 1))
+            .get_greedy_matchings(false);
+
+        let contracts: Vec<&Contract> = matchings.contracts().collect();
+        assert_eq!(contracts.len(), 1);
+        assert_eq!(contracts[0].guest_id(), &parisian);
+        let reasons: Vec<(&UserId, UnmatchedReason)> = matchings
+            .unmatched_users
             .iter()
-            .cloned()
-            .filter(|&split_id| {
-                let split = splits.get(split_id).unwrap();
-                split.nb_workstations >= user.workspace_request.as_ref().unwrap().nb_workstations
-                    && split.price() <= user.workspace_request.as_ref().unwrap().budget
+            .map(|user| (&user.user_id, user.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (&anywhere, UnmatchedReason::NoOfficeInRegion),
+                (&lyonnais, UnmatchedReason::NoOfficeInRegion),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_markets_the_same_way_whatever_the_number_of_threads() {
+        let mut storage = ObjectStorage::new();
+        for region in [Some("paris"), Some("lyon"), None, Some("lille")] {
+            add_rental_space(&mut storage, region);
+            add_guest(&mut storage, region);
+            add_guest(&mut storage, region);
+        }
+        let now = date(2025, 1, 1).and_time(NaiveTime::MIN).and_utc();
+
+        let matchings: Vec<String> = [1, 4]
+            .into_iter()
+            .map(|nb_threads| {
+                let matchings = MatchingEngine::new(&storage)
+                    .with_horizon(MatchingHorizon::new(date(2025, 1, 1), 2))
+                    .with_generator(Generator::deterministic(1, now))
+                    .with_threads(nb_threads)
+                    .get_greedy_matchings(true);
+                serde_json::to_string(&matchings).unwrap()
             })
-            .min_by_key(|&split_id| splits.get(split_id).unwrap().nb_workstations);
-
-        if let Some(split_id) = matched_rental_space {
-            unmatched_users.remove(user.id_value());
-            available_splits.remove(split_id);
-            let split = splits.get(split_id).unwrap().clone();
-            contracts.push(Contract::new(
-                split.parent_office_id.clone(),
-                split.owner_id.clone(),
-                user.id().clone(),
-                split.nb_workstations,
-                split.price(),
-            ));
+            .collect();
+
+        assert_eq!(matchings[0], matchings[1]);
+    }
+
+    #[test]
+    fn seeded_matchings_are_reproducible() {
+        let mut storage = ObjectStorage::new();
+        for _ in 0..3 {
+            add_rental_space(&mut storage, None);
+            add_guest(&mut storage, None);
+            add_guest(&mut storage, None);
         }
+        let now = date(2025, 1, 1).and_time(NaiveTime::MIN).and_utc();
+        let matchings = |seed| {
+            let matchings = MatchingEngine::new(&storage)
+                .with_horizon(MatchingHorizon::new(date(2025, 1, 1), 2))
+                .with_ordering(OrderingPolicy::Lottery)
+                .with_generator(Generator::deterministic(seed, now))
+                .get_greedy_matchings(true);
+            serde_json::to_string(&matchings).unwrap()
+        };
+
+        assert_eq!(matchings(1), matchings(1));
+        assert_ne!(matchings(1), matchings(2));
+    }
+
+    #[test]
+    fn keeps_the_stored_contracts_until_they_end() {
+        let mut storage = storage();
+        let matchings = incremental_matchings(&storage, date(2025, 1, 1));
+        assert_eq!(matchings.contracts().count(), 1);
+        storage.store_matchings(&matchings);
+
+        let matchings = incremental_matchings(&storage, date(2025, 6, 1));
+        assert_eq!(matchings.contracts().count(), 0);
+
+        // The stored contract ended on 2026-01-01.
+        let matchings = incremental_matchings(&storage, date(2026, 6, 1));
+        let contracts: Vec<&Contract> = matchings.contracts().collect();
+        assert_eq!(contracts.len(), 1);
+        assert_eq!(contracts[0].start_date(), date(2026, 6, 1));
+    }
+
+    #[test]
+    fn horizon_rejects_periods_out_of_range() {
+        let start_date = date(2025, 1, 1);
+        for horizon in [
+            MatchingHorizon::new(start_date, 3).with_period_months(2_000_000_000),
+            MatchingHorizon::new(start_date, 3).with_period_months(0),
+            MatchingHorizon::new(start_date, 0),
+            MatchingHorizon::new(start_date, 400_000),
+            MatchingHorizon::new(NaiveDate::MAX, 1),
+        ] {
+            assert!(horizon.validate().is_err(), "{:?}", horizon);
+        }
+        assert!(MatchingHorizon::new(start_date, 3)
+            .with_period_months(6)
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn period_start_dates_stop_before_the_latest_supported_date() {
+        let horizon = MatchingHorizon::new(date(2025, 1, 1), 400_000);
+        let start_dates = horizon.period_start_dates();
+        assert!(!start_dates.is_empty() && start_dates.len() < 400_000);
+
+        let horizon = MatchingHorizon::new(date(2025, 1, 1), 3).with_period_months(u32::MAX);
+        assert_eq!(horizon.period_start_dates(), vec![date(2025, 1, 1)]);
     }
 }
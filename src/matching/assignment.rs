@@ -0,0 +1,225 @@
+use crate::user::User;
+
+use super::strategy::{MatchingStrategy, PeriodInput, PeriodOutput};
+
+/// Solves the matching without splitting as an assignment problem between
+/// guests and entire splits, with the Hungarian algorithm.
+///
+/// The assignment maximizes the number of matched guests and, among the
+/// assignments matching as many guests, minimizes the number of workstations
+/// leased beyond the most the guests want.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Assignment;
+
+impl MatchingStrategy for Assignment {
+    fn allows_subsplit(&self) -> bool {
+        false
+    }
+
+    fn match_period(&self, input: PeriodInput) -> PeriodOutput {
+        let mut output = PeriodOutput::default();
+
+        let wasted_workstations = |user: &User, split_index: usize| {
+            let request = user.workspace_request.as_ref().unwrap();
+            let split = &input.splits[split_index];
+            (split.nb_workstations >= request.nb_workstations
+                && request
+                    .budget
+                    .affords(split.nb_workstations, split.price_per_workstation)
+                && request.accepts(split))
+            .then(|| {
+                split
+                    .nb_workstations
+                    .saturating_sub(*request.nb_workstations_range().end()) as i64
+            })
+        };
+
+        // Leaving a guest unmatched must cost more than any amount of wasted
+        // workstations, so that the number of matched guests comes first.
+        let unmatched_cost = input
+            .splits
+            .iter()
+            .map(|split| split.nb_workstations as i64)
+            .sum::<i64>()
+            + 1;
+
+        let size = std::cmp::max(input.users.len(), input.splits.len());
+        let costs: Vec<Vec<i64>> = (0..size)
+            .map(|row| {
+                (0..size)
+                    .map(
+                        |column| match (input.users.get(row), column < input.splits.len()) {
+                            (Some(user), true) => {
+                                wasted_workstations(user, column).unwrap_or(unmatched_cost)
+                            }
+                            (Some(_), false) => unmatched_cost,
+                            (None, _) => 0,
+                        },
+                    )
+                    .collect()
+            })
+            .collect();
+
+        for (row, column) in hungarian(&costs).into_iter().enumerate() {
+            let (Some(user), Some(split)) = (input.users.get(row), input.splits.get(column)) else {
+                continue;
+            };
+            if wasted_workstations(user, column).is_some() {
+                output.lease(split, user, &input);
+            }
+        }
+
+        output
+    }
+}
+
+/// Returns the column assigned to every row of the square `costs` matrix so
+/// that the total cost is minimal.
+fn hungarian(costs: &[Vec<i64>]) -> Vec<usize> {
+    let n = costs.len();
+    // Potentials and matching are 1-indexed, index 0 being a virtual column
+    // used to start every augmenting path.
+    let mut row_potentials = vec![0; n + 1];
+    let mut column_potentials = vec![0; n + 1];
+    let mut column_rows = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+
+    for row in 1..=n {
+        column_rows[0] = row;
+        let mut column = 0;
+        let mut min_slacks = vec![i64::MAX; n + 1];
+        let mut used = vec![false; n + 1];
+
+        loop {
+            used[column] = true;
+            let current_row = column_rows[column];
+            let mut delta = i64::MAX;
+            let mut next_column = 0;
+
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let slack = costs[current_row - 1][j - 1]
+                    - row_potentials[current_row]
+                    - column_potentials[j];
+                if slack < min_slacks[j] {
+                    min_slacks[j] = slack;
+                    way[j] = column;
+                }
+                if min_slacks[j] < delta {
+                    delta = min_slacks[j];
+                    next_column = j;
+                }
+            }
+
+            for j in 0..=n {
+                if used[j] {
+                    row_potentials[column_rows[j]] += delta;
+                    column_potentials[j] -= delta;
+                } else {
+                    min_slacks[j] -= delta;
+                }
+            }
+
+            column = next_column;
+            if column_rows[column] == 0 {
+                break;
+            }
+        }
+
+        while column != 0 {
+            let previous_column = way[column];
+            column_rows[column] = column_rows[previous_column];
+            column = previous_column;
+        }
+    }
+
+    let mut row_columns = vec![0; n];
+    for column in 1..=n {
+        if column_rows[column] > 0 {
+            row_columns[column_rows[column] - 1] = column - 1;
+        }
+    }
+    row_columns
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        matching::GreedyWithoutSplit,
+        rental_space::{AddRentalSpaceRequest, RentalSpace},
+        user::{AddUserRequest, UserId},
+        Generator, PrefixedUuid,
+    };
+
+    use {
+        super::*,
+        chrono::{NaiveDate, TimeZone, Utc},
+        serde_json::json,
+    };
+
+    fn guest(nb_workstations: u32, max_nb_workstations: u32, generator: &Generator) -> User {
+        let request: AddUserRequest = serde_json::from_value(json!({
+            "first_name": "Jane",
+            "last_name": "Doe",
+            "workspace_request": {
+                "nb_workstations": nb_workstations,
+                "max_nb_workstations": max_nb_workstations,
+                "budget": {"amount": 800, "basis": "per_workstation", "period": "year"},
+            },
+        }))
+        .unwrap();
+        User::new(request, generator).unwrap()
+    }
+
+    fn rental_space(nb_workstations: u32, generator: &Generator) -> RentalSpace {
+        let request: AddRentalSpaceRequest = serde_json::from_value(json!({
+            "name": "Rental Space",
+            "address": {
+                "street": "123 Main St",
+                "postal_code": "10001",
+                "city": "Cityville",
+                "country": "Country",
+            },
+            "surface": 10000,
+            "nb_workstations": nb_workstations,
+            "price_per_workstation": 400,
+            "owner_id": "usr-123",
+        }))
+        .unwrap();
+        RentalSpace::new(request, UserId::generate(generator), generator).unwrap()
+    }
+
+    #[test]
+    fn matches_the_guests_the_greedy_leaves_out() {
+        let generator =
+            Generator::deterministic(1, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+        // The greedy offers the flexible guest the largest split, which is the
+        // only one the other guest fits in.
+        let flexible = guest(40, 80, &generator);
+        let exact = guest(80, 80, &generator);
+        let splits: Vec<_> = [40, 80]
+            .into_iter()
+            .map(|nb_workstations| rental_space(nb_workstations, &generator).split(&generator))
+            .collect();
+        let input = || PeriodInput {
+            start_date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            users: vec![&flexible, &exact],
+            splits: splits.clone(),
+            generator: &generator,
+        };
+
+        let greedy = GreedyWithoutSplit.match_period(input());
+        assert_eq!(greedy.contracts.len(), 1);
+
+        let assignment = Assignment.match_period(input());
+        let mut leases: Vec<(&UserId, u32)> = assignment
+            .contracts
+            .iter()
+            .map(|contract| (contract.guest_id(), contract.nb_workstations()))
+            .collect();
+        leases.sort_by_key(|&(_, nb_workstations)| nb_workstations);
+        assert_eq!(leases, vec![(flexible.id(), 40), (exact.id(), 80)]);
+    }
+}
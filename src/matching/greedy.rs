@@ -0,0 +1,102 @@
+use crate::{rental_space::Split, user::WorkspaceRequest};
+
+use super::{
+    index::SplitIndex,
+    strategy::{MatchingStrategy, PeriodInput, PeriodOutput},
+};
+
+/// Offers every guest the largest entire split whose number of workstations
+/// is in the range they request and that fits their budget. If there is none,
+/// the smallest split with more workstations than requested is offered.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GreedyWithoutSplit;
+
+/// Offers every guest a split that cannot be divided and fits their request
+/// and budget, chosen as in [`GreedyWithoutSplit`]. If there is none, the
+/// largest split that can be divided so that one of its pieces fits the
+/// request is divided in two, see [`Split::subsplit`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GreedyWithSplit;
+
+impl MatchingStrategy for GreedyWithoutSplit {
+    fn allows_subsplit(&self) -> bool {
+        false
+    }
+
+    fn match_period(&self, mut input: PeriodInput) -> PeriodOutput {
+        let mut output = PeriodOutput::default();
+        let mut splits = SplitIndex::new(std::mem::take(&mut input.splits));
+
+        for user in &input.users {
+            let request = user.workspace_request.as_ref().unwrap();
+            if let Some(position) = entire_fitting(&splits, request, false) {
+                splits.remove(position);
+                output.lease(splits.get(position), user, &input);
+            }
+        }
+
+        output
+    }
+}
+
+impl MatchingStrategy for GreedyWithSplit {
+    fn allows_subsplit(&self) -> bool {
+        true
+    }
+
+    fn match_period(&self, mut input: PeriodInput) -> PeriodOutput {
+        let mut output = PeriodOutput::default();
+        let mut splits = SplitIndex::new(std::mem::take(&mut input.splits));
+
+        for user in &input.users {
+            let request = user.workspace_request.as_ref().unwrap();
+            if let Some(position) = entire_fitting(&splits, request, true) {
+                splits.remove(position);
+                output.lease(splits.get(position), user, &input);
+            } else if let Some(position) = splits.largest_dividable_fitting(
+                request.nb_workstations,
+                &request.budget,
+                |split| request.accepts(split),
+            ) {
+                splits.remove(position);
+                let (split1, split2) = splits
+                    .get(position)
+                    .subsplit(
+                        request.nb_workstations_range(),
+                        &request.budget,
+                        input.generator,
+                    )
+                    .unwrap();
+                splits.insert(split2);
+                output.lease(&split1, user, &input);
+            }
+        }
+
+        output
+    }
+}
+
+/// Returns the entire split offered to the guest, optionally only among the
+/// splits that cannot be divided to fit the request.
+fn entire_fitting(
+    splits: &SplitIndex,
+    request: &WorkspaceRequest,
+    only_undividable: bool,
+) -> Option<usize> {
+    let accepts = |split: &Split| request.accepts(split);
+    splits
+        .largest_fitting(
+            request.nb_workstations_range(),
+            &request.budget,
+            only_undividable,
+            accepts,
+        )
+        .or_else(|| {
+            splits.smallest_fitting(
+                request.nb_workstations,
+                &request.budget,
+                only_undividable,
+                accepts,
+            )
+        })
+}
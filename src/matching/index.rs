@@ -0,0 +1,187 @@
+use crate::{pricing::Price, rental_space::Split};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::RangeInclusive,
+};
+
+/// Free splits of a period, grouped by their minimal piece size and price per
+/// workstation, and ordered by number of workstations within every group, so
+/// that the splits fitting a request are found without scanning all of them.
+///
+/// Splits are referred to by their position in the index. Ties between splits
+/// of the same size are broken by position, i.e. by the order in which the
+/// splits were inserted. Queries only consider the splits accepted by a
+/// predicate, such as the location preference of the guest, and only scan
+/// further within a group when a split is rejected.
+pub(super) struct SplitIndex {
+    splits: Vec<Split>,
+    groups: BTreeMap<(u32, u32), BTreeSet<(u32, usize)>>,
+}
+
+impl SplitIndex {
+    pub(super) fn new(splits: Vec<Split>) -> Self {
+        let mut index = Self {
+            splits: Vec::with_capacity(splits.len()),
+            groups: BTreeMap::new(),
+        };
+        for split in splits {
+            index.insert(split);
+        }
+        index
+    }
+
+    pub(super) fn get(&self, position: usize) -> &Split {
+        &self.splits[position]
+    }
+
+    pub(super) fn insert(&mut self, split: Split) -> usize {
+        let position = self.splits.len();
+        self.groups
+            .entry(group(&split))
+            .or_default()
+            .insert((split.nb_workstations, position));
+        self.splits.push(split);
+        position
+    }
+
+    /// Removes the split from the available ones. It can still be accessed
+    /// with [`SplitIndex::get`].
+    pub(super) fn remove(&mut self, position: usize) {
+        let split = &self.splits[position];
+        let key = group(split);
+        let group = self.groups.get_mut(&key).unwrap();
+        group.remove(&(split.nb_workstations, position));
+        if group.is_empty() {
+            self.groups.remove(&key);
+        }
+    }
+
+    /// Returns the smallest split with at least `nb_workstations`
+    /// workstations whose price fits the budget, optionally only among the
+    /// splits that cannot be divided to fit the request.
+    pub(super) fn smallest_fitting(
+        &self,
+        nb_workstations: u32,
+        budget: &Price,
+        only_undividable: bool,
+        accepts: impl Fn(&Split) -> bool,
+    ) -> Option<usize> {
+        self.groups
+            .iter()
+            .filter(|((_, price_per_workstation), _)| {
+                budget.affords(nb_workstations, *price_per_workstation)
+            })
+            .filter_map(|(&(min_piece, price_per_workstation), group)| {
+                let max_nb_workstations = max_nb_workstations(
+                    nb_workstations,
+                    budget.affordable_nb_workstations(price_per_workstation),
+                    min_piece,
+                    only_undividable,
+                );
+                group
+                    .range((nb_workstations, 0)..=(max_nb_workstations, usize::MAX))
+                    .find(|&&(_, position)| accepts(&self.splits[position]))
+            })
+            .min()
+            .map(|&(_, position)| position)
+    }
+
+    /// Returns the largest split whose number of workstations is in
+    /// `nb_workstations` and whose price fits the budget, optionally only
+    /// among the splits that cannot be divided to fit the request.
+    pub(super) fn largest_fitting(
+        &self,
+        nb_workstations: RangeInclusive<u32>,
+        budget: &Price,
+        only_undividable: bool,
+        accepts: impl Fn(&Split) -> bool,
+    ) -> Option<usize> {
+        let accepted = |&&(_, position): &&(u32, usize)| accepts(&self.splits[position]);
+        self.groups
+            .iter()
+            .filter_map(|(&(min_piece, price_per_workstation), group)| {
+                let max_nb_workstations = max_nb_workstations(
+                    *nb_workstations.start(),
+                    budget.affordable_nb_workstations(price_per_workstation),
+                    min_piece,
+                    only_undividable,
+                )
+                .min(*nb_workstations.end());
+                if max_nb_workstations < *nb_workstations.start() {
+                    return None;
+                }
+                let &(largest, _) = group
+                    .range((*nb_workstations.start(), 0)..=(max_nb_workstations, usize::MAX))
+                    .rev()
+                    .find(accepted)?;
+                group
+                    .range((largest, 0)..=(largest, usize::MAX))
+                    .find(accepted)
+            })
+            .max_by_key(|&&(nb, position)| (nb, std::cmp::Reverse(position)))
+            .map(|&(_, position)| position)
+    }
+
+    /// Returns the largest split that can be divided so that a piece of it
+    /// holds `nb_workstations` workstations within the budget.
+    pub(super) fn largest_dividable_fitting(
+        &self,
+        nb_workstations: u32,
+        budget: &Price,
+        accepts: impl Fn(&Split) -> bool,
+    ) -> Option<usize> {
+        self.groups
+            .iter()
+            .filter(|((min_piece, price_per_workstation), _)| {
+                budget.affords(
+                    std::cmp::max(*min_piece, nb_workstations),
+                    *price_per_workstation,
+                )
+            })
+            .filter_map(|(&(min_piece, _), group)| {
+                let accepted = |&&(_, position): &&(u32, usize)| accepts(&self.splits[position]);
+                let &(largest, _) = group
+                    .iter()
+                    .rev()
+                    .take_while(|&&(nb, _)| {
+                        nb > 2 * min_piece && nb >= nb_workstations.saturating_add(min_piece)
+                    })
+                    .find(accepted)?;
+                group
+                    .range((largest, 0)..=(largest, usize::MAX))
+                    .find(accepted)
+            })
+            .max_by_key(|&&(nb, position)| (nb, std::cmp::Reverse(position)))
+            .map(|&(_, position)| position)
+    }
+}
+
+/// Returns the largest number of workstations of a split offered for a
+/// request of `nb_workstations`, given the number of workstations the budget
+/// affords.
+fn max_nb_workstations(
+    nb_workstations: u32,
+    affordable_nb_workstations: u32,
+    min_piece: u32,
+    only_undividable: bool,
+) -> u32 {
+    if only_undividable {
+        // A split can be divided for the request if it has more than twice the
+        // minimal piece size and a piece of at least the requested size leaves
+        // a valid piece.
+        affordable_nb_workstations.min(std::cmp::max(
+            2 * min_piece,
+            nb_workstations.saturating_add(min_piece - 1),
+        ))
+    } else {
+        affordable_nb_workstations
+    }
+}
+
+fn group(split: &Split) -> (u32, u32) {
+    (
+        split.min_piece_nb_workstations(),
+        split.price_per_workstation,
+    )
+}
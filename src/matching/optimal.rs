@@ -0,0 +1,458 @@
+use crate::{
+    partitioning::{layout, partition},
+    rental_space::Split,
+    user::{User, WorkspaceRequest},
+    Generator,
+};
+
+use {
+    super::strategy::{Diagnostic, MatchingStrategy, PeriodInput, PeriodOutput},
+    serde::{Deserialize, Serialize},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    MatchedUsers,
+    /// Workstations actually leased, including those a guest gets beyond
+    /// their request when a split cannot be divided further.
+    MatchedWorkstations,
+}
+
+/// Number of assignments the search explores in a period before settling for
+/// the best one found so far.
+pub const DEFAULT_MAX_NODES: u64 = 1_000_000;
+
+/// Finds the assignment of guests to splits that maximizes the objective in
+/// every period, using the same rules for splits as the greedy strategies.
+#[derive(Debug, Clone, Copy)]
+pub struct Optimal {
+    pub with_subsplit: bool,
+    pub objective: Objective,
+    /// Assignments explored in a period before the search settles for the
+    /// best one found so far, which is noted in the diagnostics.
+    pub max_nodes: u64,
+}
+
+impl Optimal {
+    pub fn new(with_subsplit: bool, objective: Objective) -> Self {
+        Self {
+            with_subsplit,
+            objective,
+            max_nodes: DEFAULT_MAX_NODES,
+        }
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+}
+
+impl MatchingStrategy for Optimal {
+    fn allows_subsplit(&self) -> bool {
+        self.with_subsplit
+    }
+
+    fn match_period(&self, input: PeriodInput) -> PeriodOutput {
+        let mut output = PeriodOutput::default();
+        let (assignments, complete) = BranchAndBound::new(
+            &input.users,
+            &input.splits,
+            self.with_subsplit,
+            self.objective,
+            self.max_nodes,
+        )
+        .solve(input.generator);
+        if !complete {
+            output.diagnostics.push(Diagnostic {
+                user_id: None,
+                message: format!(
+                    "The search stopped after exploring {} assignments, the matching may not be optimal",
+                    self.max_nodes
+                ),
+            });
+        }
+        for (user, piece) in assignments {
+            output.lease(&piece, user, &input);
+        }
+        output
+    }
+}
+
+/// Branch-and-bound search over the assignment of users to splits.
+///
+/// Every user is either assigned to one of the splits or left unmatched, and a
+/// branch is abandoned as soon as it cannot beat the best assignment found so
+/// far given the workstations left free. Splits that are equivalent and both
+/// empty are only tried once. The search is exponential in the worst case, so
+/// it stops after `max_nodes` assignments with the best one found so far.
+struct BranchAndBound<'a> {
+    users: Vec<&'a User>,
+    splits: &'a [Split],
+    with_subsplit: bool,
+    objective: Objective,
+    /// Whether every user can lease a piece of every split on their own.
+    fits: Vec<Vec<bool>>,
+    /// Indices of the earlier splits equivalent to every split.
+    equivalents: Vec<Vec<usize>>,
+    groups: Vec<Vec<usize>>,
+    /// Workstations leased in every split by its group.
+    leased: Vec<u32>,
+    best_groups: Vec<Vec<usize>>,
+    best_value: u64,
+    nb_nodes: u64,
+    max_nodes: u64,
+}
+
+impl<'a> BranchAndBound<'a> {
+    fn new(
+        users: &[&'a User],
+        splits: &'a [Split],
+        with_subsplit: bool,
+        objective: Objective,
+        max_nodes: u64,
+    ) -> Self {
+        let fits = |user: &User, split: &Split| {
+            request(user).accepts(split) && layout(split, &[request(user)], with_subsplit).is_some()
+        };
+        let mut users: Vec<&User> = users
+            .iter()
+            .copied()
+            .filter(|user| splits.iter().any(|split| fits(user, split)))
+            .collect();
+        users.sort_by_key(|user| std::cmp::Reverse(request(user).nb_workstations));
+
+        Self {
+            fits: users
+                .iter()
+                .map(|user| splits.iter().map(|split| fits(user, split)).collect())
+                .collect(),
+            equivalents: splits
+                .iter()
+                .enumerate()
+                .map(|(index, split)| {
+                    (0..index)
+                        .filter(|&other| splits[other].is_equivalent(split))
+                        .collect()
+                })
+                .collect(),
+            users,
+            splits,
+            with_subsplit,
+            objective,
+            groups: vec![Vec::new(); splits.len()],
+            leased: vec![0; splits.len()],
+            best_groups: vec![Vec::new(); splits.len()],
+            best_value: 0,
+            nb_nodes: 0,
+            max_nodes,
+        }
+    }
+
+    /// Returns the piece of a split leased to every matched user, and whether
+    /// the search completed within its budget.
+    fn solve(mut self, generator: &Generator) -> (Vec<(&'a User, Split)>, bool) {
+        self.search(0, 0);
+
+        let mut assignments = Vec::new();
+        for (split, group) in self.splits.iter().zip(&self.best_groups) {
+            if group.is_empty() {
+                continue;
+            }
+            let requests: Vec<&WorkspaceRequest> =
+                group.iter().map(|&i| request(self.users[i])).collect();
+            let sizes = layout(split, &requests, self.with_subsplit).unwrap();
+            let pieces = partition(split, &sizes, generator).unwrap().pieces;
+            for (&i, piece) in group.iter().zip(pieces) {
+                assignments.push((self.users[i], piece));
+            }
+        }
+        (assignments, self.nb_nodes < self.max_nodes)
+    }
+
+    fn search(&mut self, user_index: usize, value: u64) {
+        if self.nb_nodes >= self.max_nodes {
+            return;
+        }
+        self.nb_nodes += 1;
+
+        if value > self.best_value {
+            self.best_value = value;
+            self.best_groups = self.groups.clone();
+        }
+        if user_index == self.users.len() || value + self.bound(user_index) <= self.best_value {
+            return;
+        }
+
+        let user = self.users[user_index];
+        for split_index in 0..self.splits.len() {
+            if !self.fits[user_index][split_index] || self.is_tried(split_index) {
+                continue;
+            }
+            let mut requests: Vec<&WorkspaceRequest> = self.groups[split_index]
+                .iter()
+                .map(|&i| request(self.users[i]))
+                .collect();
+            requests.push(request(user));
+            let Some(sizes) = layout(&self.splits[split_index], &requests, self.with_subsplit)
+            else {
+                continue;
+            };
+
+            let leased: u32 = sizes.iter().sum();
+            let previous = std::mem::replace(&mut self.leased[split_index], leased);
+            let new_value = match self.objective {
+                Objective::MatchedUsers => value + 1,
+                Objective::MatchedWorkstations => value + leased as u64 - previous as u64,
+            };
+            self.groups[split_index].push(user_index);
+            self.search(user_index + 1, new_value);
+            self.groups[split_index].pop();
+            self.leased[split_index] = previous;
+        }
+
+        self.search(user_index + 1, value);
+    }
+
+    /// Returns whether an empty split equivalent to the one at `split_index`
+    /// comes before it, in which case assigning to it was already tried.
+    fn is_tried(&self, split_index: usize) -> bool {
+        self.groups[split_index].is_empty()
+            && self.equivalents[split_index]
+                .iter()
+                .any(|&other| self.groups[other].is_empty())
+    }
+
+    /// Returns an upper bound of the value the users from `user_index` can
+    /// add, given the workstations left free in every split.
+    fn bound(&self, user_index: usize) -> u64 {
+        let free: Vec<u32> = self
+            .splits
+            .iter()
+            .zip(&self.groups)
+            .zip(&self.leased)
+            .map(|((split, group), &leased)| {
+                if group.is_empty() {
+                    split.nb_workstations
+                } else if self.with_subsplit && split.subsplit_min_nb_workstations().is_some() {
+                    split.nb_workstations - leased
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let total_free: u64 = free.iter().map(|&free| free as u64).sum();
+
+        // The most workstations every remaining user could lease, if any.
+        let most_leased: Vec<(u32, u32)> = (user_index..self.users.len())
+            .filter_map(|index| {
+                let nb_workstations = request(self.users[index]).nb_workstations;
+                free.iter()
+                    .enumerate()
+                    .filter(|&(split_index, &free)| {
+                        self.fits[index][split_index] && free >= nb_workstations
+                    })
+                    .map(|(_, &free)| free)
+                    .max()
+                    .map(|most| (nb_workstations, most))
+            })
+            .collect();
+
+        if !self.with_subsplit {
+            return self.unsplit_bound(user_index, &free);
+        }
+
+        match self.objective {
+            Objective::MatchedUsers => {
+                // Users are sorted by decreasing request, so the smallest
+                // requests come last.
+                let mut capacity = total_free;
+                let mut nb_users = 0;
+                for &(nb_workstations, _) in most_leased.iter().rev() {
+                    if nb_workstations as u64 > capacity {
+                        break;
+                    }
+                    capacity -= nb_workstations as u64;
+                    nb_users += 1;
+                }
+                nb_users
+            }
+            Objective::MatchedWorkstations => {
+                let most: u64 = most_leased.iter().map(|&(_, most)| most as u64).sum();
+                most.min(total_free)
+            }
+        }
+    }
+
+    /// Returns an upper bound of the value the users from `user_index` can
+    /// add when every split is leased entirely to one user: at most as many
+    /// users as a maximum matching with the free splits, leasing at most the
+    /// largest of the free splits some of them fit.
+    fn unsplit_bound(&self, user_index: usize, free: &[u32]) -> u64 {
+        let mut holders: Vec<Option<usize>> = vec![None; free.len()];
+        let mut nb_users = 0;
+        for index in user_index..self.users.len() {
+            let mut visited = vec![false; free.len()];
+            if self.augment(index, free, &mut holders, &mut visited) {
+                nb_users += 1;
+            }
+        }
+
+        match self.objective {
+            Objective::MatchedUsers => nb_users,
+            Objective::MatchedWorkstations => {
+                let mut sizes: Vec<u32> = (0..free.len())
+                    .filter(|&split_index| {
+                        free[split_index] > 0
+                            && (user_index..self.users.len())
+                                .any(|index| self.fits[index][split_index])
+                    })
+                    .map(|split_index| free[split_index])
+                    .collect();
+                sizes.sort_unstable_by(|a, b| b.cmp(a));
+                sizes
+                    .iter()
+                    .take(nb_users as usize)
+                    .map(|&size| size as u64)
+                    .sum()
+            }
+        }
+    }
+
+    /// Looks for an augmenting path from the user at `index` to a free split,
+    /// updating the holder of every split along it.
+    fn augment(
+        &self,
+        index: usize,
+        free: &[u32],
+        holders: &mut [Option<usize>],
+        visited: &mut [bool],
+    ) -> bool {
+        for split_index in 0..free.len() {
+            if free[split_index] == 0 || !self.fits[index][split_index] || visited[split_index] {
+                continue;
+            }
+            visited[split_index] = true;
+            let available = match holders[split_index] {
+                None => true,
+                Some(holder) => self.augment(holder, free, holders, visited),
+            };
+            if available {
+                holders[split_index] = Some(index);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn request(user: &User) -> &WorkspaceRequest {
+    user.workspace_request.as_ref().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        rental_space::{AddRentalSpaceRequest, RentalSpace},
+        user::{AddUserRequest, UserId},
+        PrefixedUuid,
+    };
+
+    use {
+        super::*,
+        chrono::{NaiveDate, TimeZone, Utc},
+        serde_json::json,
+    };
+
+    fn generator() -> Generator {
+        Generator::deterministic(1, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+    }
+
+    fn guest(nb_workstations: u32, generator: &Generator) -> User {
+        let request: AddUserRequest = serde_json::from_value(json!({
+            "first_name": "Jane",
+            "last_name": "Doe",
+            "workspace_request": {
+                "nb_workstations": nb_workstations,
+                "budget": {"amount": 800, "basis": "per_workstation", "period": "year"},
+            },
+        }))
+        .unwrap();
+        User::new(request, generator).unwrap()
+    }
+
+    fn split(nb_workstations: u32, surface: u32, generator: &Generator) -> Split {
+        let request: AddRentalSpaceRequest = serde_json::from_value(json!({
+            "name": "Rental Space",
+            "address": {
+                "street": "123 Main St",
+                "postal_code": "10001",
+                "city": "Cityville",
+                "country": "Country",
+            },
+            "surface": surface,
+            "nb_workstations": nb_workstations,
+            "price_per_workstation": 400,
+            "owner_id": "usr-123",
+        }))
+        .unwrap();
+        RentalSpace::new(request, UserId::generate(generator), generator)
+            .unwrap()
+            .split(generator)
+    }
+
+    fn match_period(optimal: Optimal, users: &[User], splits: Vec<Split>) -> PeriodOutput {
+        let generator = generator();
+        optimal.match_period(PeriodInput {
+            start_date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            users: users.iter().collect(),
+            splits,
+            generator: &generator,
+        })
+    }
+
+    #[test]
+    fn weighs_the_workstations_actually_leased() {
+        let generator = generator();
+        let users = [guest(45, &generator)];
+        let splits = vec![split(50, 200, &generator), split(100, 400, &generator)];
+
+        let output = match_period(
+            Optimal::new(false, Objective::MatchedWorkstations),
+            &users,
+            splits,
+        );
+        assert_eq!(output.contracts.len(), 1);
+        assert_eq!(output.contracts[0].nb_workstations(), 100);
+    }
+
+    #[test]
+    fn solves_identical_splits_within_the_budget() {
+        let generator = generator();
+        let users: Vec<User> = (0..30).map(|_| guest(40, &generator)).collect();
+        let splits = (0..8).map(|_| split(160, 640, &generator)).collect();
+
+        let output = match_period(Optimal::new(true, Objective::MatchedUsers), &users, splits);
+        assert_eq!(output.contracts.len(), 30);
+        assert!(output.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_best_assignment_when_the_budget_runs_out() {
+        let generator = generator();
+        let users: Vec<User> = (0..6).map(|i| guest(40 + i, &generator)).collect();
+        let splits = (0..3).map(|_| split(160, 640, &generator)).collect();
+
+        let output = match_period(
+            Optimal::new(true, Objective::MatchedUsers).with_max_nodes(5),
+            &users,
+            splits,
+        );
+        assert!(!output.contracts.is_empty());
+        assert!(output
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.message.contains("may not be optimal")));
+    }
+}
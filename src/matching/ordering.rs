@@ -0,0 +1,147 @@
+use crate::{
+    pricing::Currency,
+    user::{User, WorkspaceRequest},
+    Generator,
+};
+
+use {
+    clap::ValueEnum,
+    rand::Rng,
+    serde::{Deserialize, Serialize},
+    std::{cmp::Reverse, collections::BTreeMap, fmt},
+};
+
+/// Order in which guests are offered rental spaces by the greedy matching.
+/// Guests that compare equal keep the order in which they were created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderingPolicy {
+    /// Guests created first are matched first.
+    #[default]
+    FirstComeFirstServed,
+    /// Guests requesting the most workstations are matched first.
+    LargestRequestFirst,
+    /// Guests with the highest yearly budget for the workstations they
+    /// request are matched first. Budgets in different currencies are not
+    /// compared: guests only change places with guests budgeting in the same
+    /// currency.
+    HighestBudgetFirst,
+    /// Guests with the highest `priority` are matched first.
+    Priority,
+    /// Guests are drawn at random, with a chance proportional to their
+    /// `priority` plus one.
+    Lottery,
+}
+
+impl OrderingPolicy {
+    /// Sorts `users`, which must be ordered by creation time and all have a
+    /// workspace request.
+    pub(super) fn sort(&self, users: &mut [&User], generator: &Generator) {
+        match self {
+            OrderingPolicy::FirstComeFirstServed => {}
+            OrderingPolicy::LargestRequestFirst => {
+                users.sort_by_key(|user| Reverse(request(user).nb_workstations))
+            }
+            OrderingPolicy::HighestBudgetFirst => {
+                let guests = users.to_vec();
+                let mut places: BTreeMap<&Currency, Vec<usize>> = BTreeMap::new();
+                for (place, user) in guests.iter().enumerate() {
+                    places
+                        .entry(&request(user).budget.currency)
+                        .or_default()
+                        .push(place);
+                }
+                for places in places.values() {
+                    let mut sorted: Vec<&User> =
+                        places.iter().map(|&place| guests[place]).collect();
+                    sorted.sort_by_key(|user| {
+                        let request = request(user);
+                        Reverse(request.budget.yearly_total(request.nb_workstations))
+                    });
+                    for (&place, user) in places.iter().zip(sorted) {
+                        users[place] = user;
+                    }
+                }
+            }
+            OrderingPolicy::Priority => users.sort_by_key(|user| Reverse(request(user).priority)),
+            OrderingPolicy::Lottery => {
+                // Weighted sampling without replacement: every guest draws
+                // u^(1/w) with u uniform in (0, 1), highest keys first.
+                let mut rng = generator.rng();
+                let mut keys: Vec<(f64, &User)> = users
+                    .iter()
+                    .map(|&user| {
+                        let weight = request(user).priority as f64 + 1.0;
+                        (rng.gen::<f64>().powf(1.0 / weight), user)
+                    })
+                    .collect();
+                keys.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+                for (slot, (_, user)) in users.iter_mut().zip(keys) {
+                    *slot = user;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for OrderingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ordering = match self {
+            OrderingPolicy::FirstComeFirstServed => "first come, first served",
+            OrderingPolicy::LargestRequestFirst => "largest request first",
+            OrderingPolicy::HighestBudgetFirst => "highest budget first",
+            OrderingPolicy::Priority => "priority",
+            OrderingPolicy::Lottery => "lottery",
+        };
+        write!(f, "{}", ordering)
+    }
+}
+
+fn request(user: &User) -> &WorkspaceRequest {
+    user.workspace_request.as_ref().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::user::AddUserRequest,
+        chrono::{TimeZone, Utc},
+        serde_json::json,
+    };
+
+    fn guest(budget: u32, currency: &str, generator: &Generator) -> User {
+        let request: AddUserRequest = serde_json::from_value(json!({
+            "first_name": "Jane",
+            "last_name": "Doe",
+            "workspace_request": {
+                "nb_workstations": 40,
+                "budget": {"amount": budget, "currency": currency},
+            },
+        }))
+        .unwrap();
+        User::new(request, generator).unwrap()
+    }
+
+    #[test]
+    fn only_compares_budgets_in_the_same_currency() {
+        let generator =
+            Generator::deterministic(1, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+        let guests = [
+            guest(20_000, "EUR", &generator),
+            guest(2_000_000, "JPY", &generator),
+            guest(30_000, "EUR", &generator),
+            guest(3_000_000, "JPY", &generator),
+            guest(25_000, "EUR", &generator),
+        ];
+        let mut users: Vec<&User> = guests.iter().collect();
+
+        OrderingPolicy::HighestBudgetFirst.sort(&mut users, &generator);
+
+        let budgets: Vec<u32> = users
+            .iter()
+            .map(|user| request(user).budget.amount)
+            .collect();
+        assert_eq!(budgets, vec![30_000, 3_000_000, 25_000, 2_000_000, 20_000]);
+    }
+}
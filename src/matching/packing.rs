@@ -0,0 +1,67 @@
+use crate::{
+    partitioning,
+    user::{User, WorkspaceRequest},
+};
+
+use super::strategy::{MatchingStrategy, PeriodInput, PeriodOutput};
+
+/// Packs several guests into every split with a single planned layout.
+///
+/// Every guest, in the order of the input, joins the split that leaves the
+/// fewest workstations free once the guests already planned in it and the new
+/// guest share it. The splits are then divided with [`partitioning::plan`], so
+/// a large rental space can be shared by more than two guests in the same
+/// period.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Packing;
+
+impl MatchingStrategy for Packing {
+    fn allows_subsplit(&self) -> bool {
+        true
+    }
+
+    fn match_period(&self, input: PeriodInput) -> PeriodOutput {
+        let mut output = PeriodOutput::default();
+        let mut groups: Vec<Vec<&User>> = vec![Vec::new(); input.splits.len()];
+
+        for &user in &input.users {
+            let best_split = input
+                .splits
+                .iter()
+                .zip(&groups)
+                .enumerate()
+                .filter(|(_, (split, _))| request(user).accepts(split))
+                .filter_map(|(index, (split, group))| {
+                    let requests: Vec<&WorkspaceRequest> = group
+                        .iter()
+                        .chain([&user])
+                        .map(|user| request(user))
+                        .collect();
+                    let sizes = partitioning::layout(split, &requests, true)?;
+                    Some((split.nb_workstations - sizes.iter().sum::<u32>(), index))
+                })
+                .min();
+
+            if let Some((_, index)) = best_split {
+                groups[index].push(user);
+            }
+        }
+
+        for (split, group) in input.splits.iter().zip(&groups) {
+            if group.is_empty() {
+                continue;
+            }
+            let requests: Vec<&WorkspaceRequest> = group.iter().map(|user| request(user)).collect();
+            let partition = partitioning::plan(split, &requests, true, input.generator).unwrap();
+            for (user, piece) in group.iter().zip(&partition.pieces) {
+                output.lease(piece, user, &input);
+            }
+        }
+
+        output
+    }
+}
+
+fn request(user: &User) -> &WorkspaceRequest {
+    user.workspace_request.as_ref().unwrap()
+}
@@ -0,0 +1,208 @@
+use crate::{rental_space::Split, user::WorkspaceRequest};
+
+use super::UnmatchedReason;
+
+/// Rental spaces as if they were entirely free, indexed by number of
+/// workstations to find why a guest could not be matched without checking
+/// every rental space.
+pub(super) struct Catalog {
+    nb_workstations: Vec<u32>,
+    min_price_per_workstation_from: Vec<u32>,
+    min_price_from: Vec<u32>,
+    classes: Vec<PieceClass>,
+}
+
+/// Rental spaces sharing the same minimal piece size.
+struct PieceClass {
+    min_piece: u32,
+    nb_workstations: Vec<u32>,
+    min_price_per_workstation_from: Vec<u32>,
+    prices: RangeMin,
+    prices_per_workstation: RangeMin,
+}
+
+impl Catalog {
+    pub(super) fn new(mut splits: Vec<Split>) -> Self {
+        splits.sort_by_key(|split| split.nb_workstations);
+
+        let mut classes: Vec<PieceClass> = Vec::new();
+        for min_piece in [40, 60] {
+            let splits: Vec<&Split> = splits
+                .iter()
+                .filter(|split| split.min_piece_nb_workstations() == min_piece)
+                .collect();
+            classes.push(PieceClass {
+                min_piece,
+                nb_workstations: splits.iter().map(|split| split.nb_workstations).collect(),
+                min_price_per_workstation_from: suffix_min(
+                    splits.iter().map(|split| split.price_per_workstation),
+                ),
+                prices: RangeMin::new(splits.iter().map(|split| split.price()).collect()),
+                prices_per_workstation: RangeMin::new(
+                    splits
+                        .iter()
+                        .map(|split| split.price_per_workstation)
+                        .collect(),
+                ),
+            });
+        }
+
+        Self {
+            nb_workstations: splits.iter().map(|split| split.nb_workstations).collect(),
+            min_price_per_workstation_from: suffix_min(
+                splits.iter().map(|split| split.price_per_workstation),
+            ),
+            min_price_from: suffix_min(splits.iter().map(Split::price)),
+            classes,
+        }
+    }
+
+    /// Finds why a request fitting in the horizon could not be matched.
+    pub(super) fn reason(
+        &self,
+        request: &WorkspaceRequest,
+        with_subsplit: bool,
+    ) -> UnmatchedReason {
+        let first_big_enough = self
+            .nb_workstations
+            .partition_point(|&nb| nb < request.nb_workstations);
+
+        if first_big_enough == self.nb_workstations.len() {
+            return UnmatchedReason::NoOfficeBigEnough;
+        }
+
+        if !request.budget.affords(
+            request.nb_workstations,
+            self.min_price_per_workstation_from[first_big_enough],
+        ) {
+            return UnmatchedReason::OverBudget;
+        }
+
+        let affordable = if with_subsplit {
+            self.classes
+                .iter()
+                .any(|class| class.has_affordable_piece(request))
+        } else {
+            request.budget.affords_any(
+                self.min_price_from[first_big_enough],
+                self.min_price_per_workstation_from[first_big_enough],
+            )
+        };
+
+        if affordable {
+            UnmatchedReason::OfficeTakenEarlier
+        } else {
+            UnmatchedReason::SplitConstraintViolated
+        }
+    }
+}
+
+impl PieceClass {
+    /// Whether a rental space can be leased within the budget, entirely or
+    /// divided so that a piece fits the request.
+    fn has_affordable_piece(&self, request: &WorkspaceRequest) -> bool {
+        let nb = request.nb_workstations;
+
+        // Rental spaces that can be divided for the request cost at least
+        // the larger of the request and the minimal piece.
+        let first_dividable = self.nb_workstations.partition_point(|&nb_workstations| {
+            nb_workstations <= 2 * self.min_piece
+                || nb_workstations < nb.saturating_add(self.min_piece)
+        });
+        if first_dividable < self.nb_workstations.len()
+            && request.budget.affords(
+                std::cmp::max(self.min_piece, nb),
+                self.min_price_per_workstation_from[first_dividable],
+            )
+        {
+            return true;
+        }
+
+        // The other rental spaces big enough must be leased entirely.
+        let first_big_enough = self
+            .nb_workstations
+            .partition_point(|&nb_workstations| nb_workstations < nb);
+        first_big_enough < first_dividable
+            && request.budget.affords_any(
+                self.prices.min(first_big_enough, first_dividable),
+                self.prices_per_workstation
+                    .min(first_big_enough, first_dividable),
+            )
+    }
+}
+
+/// Finds why a request restricted to some locations, currency or amenities
+/// could not be matched, by building a [`Catalog`] of the splits it accepts.
+pub(super) fn restricted_reason(
+    request: &WorkspaceRequest,
+    splits: &[Split],
+    with_subsplit: bool,
+) -> UnmatchedReason {
+    let in_location = |split: &Split| {
+        request
+            .location
+            .as_ref()
+            .is_none_or(|location| location.accepts(split.address()))
+    };
+    if !splits.iter().any(in_location) {
+        return UnmatchedReason::NoOfficeInLocation;
+    }
+
+    if !splits
+        .iter()
+        .any(|split| in_location(split) && split.currency() == &request.budget.currency)
+    {
+        return UnmatchedReason::NoOfficeInCurrency;
+    }
+
+    let accepted: Vec<Split> = splits
+        .iter()
+        .filter(|split| request.accepts(split))
+        .cloned()
+        .collect();
+    if accepted.is_empty() {
+        return UnmatchedReason::MissingAmenities;
+    }
+
+    Catalog::new(accepted).reason(request, with_subsplit)
+}
+
+/// Returns the minimum of every suffix of `values`, followed by `u32::MAX`
+/// for the empty suffix.
+fn suffix_min(values: impl DoubleEndedIterator<Item = u32>) -> Vec<u32> {
+    let mut mins: Vec<u32> = vec![u32::MAX];
+    for value in values.rev() {
+        mins.push(std::cmp::min(value, *mins.last().unwrap()));
+    }
+    mins.reverse();
+    mins
+}
+
+/// Sparse table answering minimum queries over ranges of a fixed array.
+struct RangeMin {
+    levels: Vec<Vec<u32>>,
+}
+
+impl RangeMin {
+    fn new(values: Vec<u32>) -> Self {
+        let mut levels = vec![values];
+        let mut width = 1;
+        while 2 * width <= levels[0].len() {
+            let previous = levels.last().unwrap();
+            let level = (0..previous.len() - width)
+                .map(|i| std::cmp::min(previous[i], previous[i + width]))
+                .collect();
+            levels.push(level);
+            width *= 2;
+        }
+        Self { levels }
+    }
+
+    /// Returns the minimum of the values in `start..end`, which must not be
+    /// empty.
+    fn min(&self, start: usize, end: usize) -> u32 {
+        let level = (end - start).ilog2() as usize;
+        let width = 1 << level;
+        std::cmp::min(self.levels[level][start], self.levels[level][end - width])
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{contract::Contract, user::User, Generator};
+
+use {
+    super::{
+        strategy::{Diagnostic, PeriodOutput},
+        Occupancy,
+    },
+    chrono::{Months, NaiveDate},
+    serde::{Deserialize, Serialize},
+    validator::{Validate, ValidationError},
+};
+
+/// Lets the guests who asked for it keep their workstations when their
+/// contract ends, see [`WorkspaceRequest::renew`](crate::user::WorkspaceRequest::renew).
+/// A contract is renewed from its end date, for the term requested by the
+/// guest, so that the guest keeps the workstations without interruption. The
+/// contracts ending during a period are renewed before the workstations left
+/// free at its start are offered to other guests.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct RenewalPolicy {
+    /// Percentage by which the price per workstation rises at every renewal.
+    #[validate(custom(function = "validate_indexation_percent"))]
+    pub indexation_percent: f64,
+}
+
+/// Contract that will be renewed when it ends, with the guest holding it.
+pub(super) struct Tenancy<'a> {
+    pub contract: Contract,
+    pub guest: &'a User,
+}
+
+fn validate_indexation_percent(indexation_percent: f64) -> Result<(), ValidationError> {
+    if !indexation_percent.is_finite() || indexation_percent <= -100.0 {
+        return Err(ValidationError::new("indexation_percent")
+            .with_message("The indexation must be a number greater than -100".into()));
+    }
+    Ok(())
+}
+
+impl RenewalPolicy {
+    pub fn new(indexation_percent: f64) -> Self {
+        Self { indexation_percent }
+    }
+
+    /// Renews the contracts of `tenancies` that end before `end_date`, the end
+    /// of the period, as many times as needed to reach it, and keeps the
+    /// renewed ones to renew them again later. The workstations stay leased in
+    /// `occupancy` until the renewed contract ends. Contracts whose renewed
+    /// price does not fit the budget of the guest are dropped.
+    pub(super) fn renew(
+        &self,
+        tenancies: &mut Vec<Tenancy>,
+        end_date: NaiveDate,
+        occupancy: &mut Occupancy,
+        generator: &Generator,
+    ) -> PeriodOutput {
+        let mut output = PeriodOutput::default();
+
+        for tenancy in tenancies.iter_mut() {
+            let request = tenancy.guest.workspace_request.as_ref().unwrap();
+            while tenancy.contract.end_date() < end_date {
+                let start_date = tenancy.contract.end_date();
+                // A renewal ending on its start date, which only a term of no
+                // month gives, would never reach the end of the period.
+                match start_date.checked_add_months(Months::new(request.term_months)) {
+                    Some(renewal_end_date) if renewal_end_date > start_date => {}
+                    _ => break,
+                }
+
+                let renewal = tenancy.contract.renewal(
+                    &request.budget,
+                    start_date,
+                    request.term_months,
+                    self.indexation_percent,
+                    generator,
+                );
+                if renewal.currency() != &request.budget.currency
+                    || !request
+                        .budget
+                        .affords(renewal.nb_workstations(), renewal.price_per_workstation())
+                {
+                    output.diagnostics.push(Diagnostic {
+                        user_id: Some(tenancy.guest.id().clone()),
+                        message: format!(
+                            "Contract {} was not renewed as its renewed price does not fit the budget",
+                            tenancy.contract.id_value()
+                        ),
+                    });
+                    break;
+                }
+
+                occupancy.extend(&tenancy.contract, renewal.end_date());
+                output.contracts.push(renewal.clone());
+                tenancy.contract = renewal;
+            }
+        }
+
+        tenancies.retain(|tenancy| tenancy.contract.end_date() >= end_date);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            rental_space::{AddRentalSpaceRequest, RentalSpace},
+            user::{AddUserRequest, UserId},
+            PrefixedUuid,
+        },
+        chrono::{TimeZone, Utc},
+        serde_json::json,
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Returns a guest asking to renew their contracts of 12 months, and one
+    /// of these contracts starting on 2025-01-01.
+    fn tenancy(generator: &Generator) -> (User, Contract) {
+        let request: AddUserRequest = serde_json::from_value(json!({
+            "first_name": "Jane",
+            "last_name": "Doe",
+            "workspace_request": {"nb_workstations": 40, "budget": 40000, "renew": true},
+        }))
+        .unwrap();
+        let guest = User::new(request, generator).unwrap();
+        let request: AddRentalSpaceRequest = serde_json::from_value(json!({
+            "name": "Rental Space",
+            "address": {
+                "street": "123 Main St",
+                "postal_code": "10001",
+                "city": "Cityville",
+                "country": "Country",
+            },
+            "surface": 10000,
+            "nb_workstations": 40,
+            "price_per_workstation": 400,
+            "owner_id": "usr-123",
+        }))
+        .unwrap();
+        let rental_space =
+            RentalSpace::new(request, UserId::generate(generator), generator).unwrap();
+        let request = guest.workspace_request.as_ref().unwrap();
+        let contract = Contract::new(
+            &rental_space.split(generator),
+            guest.id().clone(),
+            &request.budget,
+            date(2025, 1, 1),
+            request.term_months,
+            generator,
+        );
+        (guest, contract)
+    }
+
+    #[test]
+    fn renews_contracts_until_the_end_of_the_period() {
+        let generator =
+            Generator::deterministic(1, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+        let (guest, contract) = tenancy(&generator);
+        let mut tenancies = vec![Tenancy {
+            contract,
+            guest: &guest,
+        }];
+
+        let output = RenewalPolicy::new(0.0).renew(
+            &mut tenancies,
+            date(2027, 6, 1),
+            &mut Occupancy::default(),
+            &generator,
+        );
+
+        let end_dates: Vec<NaiveDate> = output.contracts.iter().map(Contract::end_date).collect();
+        assert_eq!(end_dates, vec![date(2027, 1, 1), date(2028, 1, 1)]);
+        assert_eq!(
+            output.contracts[1].renewed_from(),
+            Some(output.contracts[0].id())
+        );
+        assert_eq!(tenancies.len(), 1);
+    }
+
+    #[test]
+    fn stops_renewing_contracts_of_no_month() {
+        let generator =
+            Generator::deterministic(1, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+        let (mut guest, contract) = tenancy(&generator);
+        guest.workspace_request.as_mut().unwrap().term_months = 0;
+        let mut tenancies = vec![Tenancy {
+            contract,
+            guest: &guest,
+        }];
+
+        let output = RenewalPolicy::new(0.0).renew(
+            &mut tenancies,
+            date(2027, 1, 1),
+            &mut Occupancy::default(),
+            &generator,
+        );
+
+        assert!(output.contracts.is_empty());
+        assert!(tenancies.is_empty());
+    }
+
+    #[test]
+    fn indexation_must_keep_prices_positive() {
+        for indexation_percent in [-100.0, -150.0, f64::NAN, f64::INFINITY] {
+            assert!(RenewalPolicy::new(indexation_percent).validate().is_err());
+        }
+        for indexation_percent in [-99.5, 0.0, 3.5] {
+            assert!(RenewalPolicy::new(indexation_percent).validate().is_ok());
+        }
+    }
+}
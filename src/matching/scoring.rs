@@ -0,0 +1,159 @@
+use crate::{rental_space::Split, user::WorkspaceRequest};
+
+use {
+    super::strategy::{MatchingStrategy, PeriodInput, PeriodOutput},
+    serde::{Deserialize, Serialize},
+};
+
+/// Offers every guest, in the order of the input, the split with the lowest
+/// score among the ones they accept that fit their request and budget. The
+/// score is a weighted sum of four criteria, each scaled to lie between 0 and
+/// 1:
+///
+/// - the distance between the split and the center of the area preferred by
+///   the guest, relative to the radius of the area,
+/// - the price of the leased workstations, relative to the budget,
+/// - the share of the leased workstations beyond the most the guest wants,
+/// - the share of the amenities preferred by the guest that the split lacks.
+///
+/// The distance is 0 for guests without a preferred area. Every split is
+/// scored for every guest.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Scoring {
+    pub with_subsplit: bool,
+    pub weights: ScoringWeights,
+}
+
+/// Relative importance of the criteria of the [`Scoring`] strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringWeights {
+    pub distance: f64,
+    pub price: f64,
+    pub waste: f64,
+    pub amenities: f64,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            distance: 1.0,
+            price: 1.0,
+            waste: 1.0,
+            amenities: 1.0,
+        }
+    }
+}
+
+impl Scoring {
+    pub fn new(with_subsplit: bool) -> Self {
+        Self {
+            with_subsplit,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_weights(mut self, weights: ScoringWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Returns the number of workstations the guest would lease in the split
+    /// and the score of the split, if the split fits the request.
+    pub(super) fn score(&self, split: &Split, request: &WorkspaceRequest) -> Option<(u32, f64)> {
+        if !request.accepts(split) {
+            return None;
+        }
+
+        let piece_nb_workstations = self
+            .with_subsplit
+            .then(|| {
+                split.subsplit_nb_workstations(request.nb_workstations_range(), &request.budget)
+            })
+            .flatten();
+        let nb_leased = if let Some(nb_workstations) = piece_nb_workstations {
+            nb_workstations
+        } else if split.nb_workstations >= request.nb_workstations {
+            split.nb_workstations
+        } else {
+            return None;
+        };
+        if !request
+            .budget
+            .affords(nb_leased, split.price_per_workstation)
+        {
+            return None;
+        }
+
+        let distance = request
+            .location
+            .as_ref()
+            .and_then(|location| location.within)
+            .zip(split.address().coordinates)
+            .filter(|(area, _)| area.radius_km > 0.0)
+            .map_or(0.0, |(area, coordinates)| {
+                coordinates.distance_km(&area.center) / area.radius_km
+            });
+        let price = (nb_leased as f64 * split.price_per_workstation as f64)
+            / request.budget.yearly_total(nb_leased) as f64;
+        let waste = nb_leased.saturating_sub(*request.nb_workstations_range().end()) as f64
+            / nb_leased as f64;
+        let amenities = if request.preferred_amenities.is_empty() {
+            0.0
+        } else {
+            request.nb_missing_preferred_amenities(split) as f64
+                / request.preferred_amenities.len() as f64
+        };
+
+        Some((
+            nb_leased,
+            self.weights.distance * distance
+                + self.weights.price * price
+                + self.weights.waste * waste
+                + self.weights.amenities * amenities,
+        ))
+    }
+}
+
+impl MatchingStrategy for Scoring {
+    fn allows_subsplit(&self) -> bool {
+        self.with_subsplit
+    }
+
+    fn match_period(&self, mut input: PeriodInput) -> PeriodOutput {
+        let mut output = PeriodOutput::default();
+        let mut splits = std::mem::take(&mut input.splits);
+
+        for user in &input.users {
+            let request = user.workspace_request.as_ref().unwrap();
+            let best = splits
+                .iter()
+                .enumerate()
+                .filter_map(|(index, split)| {
+                    self.score(split, request)
+                        .map(|(nb_leased, score)| (score, index, nb_leased))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+            let Some((_, index, nb_leased)) = best else {
+                continue;
+            };
+            let split = splits.remove(index);
+            if nb_leased < split.nb_workstations {
+                let (piece, rest) = split
+                    .subsplit(
+                        request.nb_workstations_range(),
+                        &request.budget,
+                        input.generator,
+                    )
+                    .unwrap();
+                splits.push(rest);
+                output.lease(&piece, user, &input);
+            } else {
+                output.lease(&split, user, &input);
+            }
+        }
+
+        output
+    }
+}
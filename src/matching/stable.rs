@@ -0,0 +1,206 @@
+use crate::{
+    contract::Contract,
+    rental_space::{RentalSpaceId, Split},
+    user::{User, UserId},
+};
+
+use {
+    super::{
+        scoring::{Scoring, ScoringWeights},
+        strategy::{MatchingStrategy, PeriodInput, PeriodOutput},
+    },
+    serde::{Deserialize, Serialize},
+    std::{cmp::Reverse, collections::HashMap},
+};
+
+/// Two-sided matching without splitting, with the deferred acceptance
+/// algorithm of Gale and Shapley.
+///
+/// Guests rank the entire splits that fit their request and budget by their
+/// [`Scoring`] score, which accounts for the price, the distance, the wasted
+/// workstations and the missing amenities. Hosts rank the guests that can
+/// lease their split by budget for the split, then by term, then by priority,
+/// then in the order of the input.
+///
+/// Guests propose to the splits in their order of preference, and every split
+/// keeps the best proposal so far. The result is stable: no guest and split
+/// would both rather be leased to each other than keep what they got. Among
+/// the stable matchings, it is the best one for every guest.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StableMatching {
+    pub weights: ScoringWeights,
+}
+
+/// Guest and rental space that would both rather be leased to each other than
+/// keep what they got in a period, according to the preferences of
+/// [`StableMatching`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockingPair {
+    pub user_id: UserId,
+    pub rental_space_id: RentalSpaceId,
+}
+
+/// Rank of a guest for the host of a split, the lowest being preferred.
+type HostRank = (Reverse<u32>, Reverse<u32>, Reverse<u32>, usize);
+
+impl StableMatching {
+    pub fn new(weights: ScoringWeights) -> Self {
+        Self { weights }
+    }
+
+    fn scoring(&self) -> Scoring {
+        Scoring::new(false).with_weights(self.weights)
+    }
+
+    /// Returns the indices of the splits the guest accepts, most preferred
+    /// first.
+    fn guest_preferences(&self, user: &User, splits: &[Split]) -> Vec<usize> {
+        let request = user.workspace_request.as_ref().unwrap();
+        let scoring = self.scoring();
+        let mut ranked: Vec<(f64, usize)> = splits
+            .iter()
+            .enumerate()
+            .filter_map(|(index, split)| {
+                scoring
+                    .score(split, request)
+                    .map(|(_, score)| (score, index))
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        ranked.into_iter().map(|(_, index)| index).collect()
+    }
+
+    /// Finds the blocking pairs of the `contracts` generated in a period for
+    /// `users` from `splits`. A guest leasing a piece of a split ranks it as
+    /// the split of the same rental space with as many workstations as the
+    /// piece.
+    pub(super) fn blocking_pairs(
+        &self,
+        users: &[&User],
+        splits: &[Split],
+        contracts: &[Contract],
+    ) -> Vec<BlockingPair> {
+        let scoring = self.scoring();
+        let split_indices: HashMap<&RentalSpaceId, usize> = splits
+            .iter()
+            .enumerate()
+            .map(|(index, split)| (&split.parent_office_id, index))
+            .collect();
+        let contracts: HashMap<&UserId, &Contract> = contracts
+            .iter()
+            .map(|contract| (contract.guest_id(), contract))
+            .collect();
+
+        // The split leased by every guest, and the guests of every split.
+        let leased: Vec<Option<usize>> = users
+            .iter()
+            .map(|user| {
+                let contract = contracts.get(user.id())?;
+                split_indices.get(contract.rental_space_id()).copied()
+            })
+            .collect();
+        let mut guests: Vec<Vec<usize>> = vec![Vec::new(); splits.len()];
+        for (user_index, split_index) in leased.iter().enumerate() {
+            if let Some(split_index) = split_index {
+                guests[*split_index].push(user_index);
+            }
+        }
+
+        let mut pairs = Vec::new();
+        for (user_index, user) in users.iter().enumerate() {
+            let request = user.workspace_request.as_ref().unwrap();
+            let current_score = leased[user_index].and_then(|split_index| {
+                let mut piece = splits[split_index].clone();
+                piece.nb_workstations = contracts[user.id()].nb_workstations();
+                scoring.score(&piece, request).map(|(_, score)| score)
+            });
+
+            for (split_index, split) in splits.iter().enumerate() {
+                if leased[user_index] == Some(split_index) {
+                    continue;
+                }
+                let Some((_, score)) = scoring.score(split, request) else {
+                    continue;
+                };
+                if current_score.is_some_and(|current_score| current_score <= score) {
+                    continue;
+                }
+
+                let rank = host_rank(split, user, user_index);
+                let host_prefers = guests[split_index].is_empty()
+                    || guests[split_index]
+                        .iter()
+                        .any(|&guest| rank < host_rank(split, users[guest], guest));
+                if host_prefers {
+                    pairs.push(BlockingPair {
+                        user_id: user.id().clone(),
+                        rental_space_id: split.parent_office_id.clone(),
+                    });
+                }
+            }
+        }
+
+        pairs
+    }
+}
+
+/// Returns the rank of the guest at `index` in the input for the host of
+/// `split`.
+fn host_rank(split: &Split, user: &User, index: usize) -> HostRank {
+    let request = user.workspace_request.as_ref().unwrap();
+    (
+        Reverse(request.budget.yearly_total(split.nb_workstations)),
+        Reverse(request.term_months),
+        Reverse(request.priority),
+        index,
+    )
+}
+
+impl MatchingStrategy for StableMatching {
+    fn allows_subsplit(&self) -> bool {
+        false
+    }
+
+    fn match_period(&self, input: PeriodInput) -> PeriodOutput {
+        let preferences: Vec<Vec<usize>> = input
+            .users
+            .iter()
+            .map(|user| self.guest_preferences(user, &input.splits))
+            .collect();
+        let mut next_choices = vec![0; input.users.len()];
+        let mut holders: Vec<Option<usize>> = vec![None; input.splits.len()];
+
+        // Guests propose in the order of the input.
+        let mut free_guests: Vec<usize> = (0..input.users.len()).rev().collect();
+        while let Some(guest) = free_guests.pop() {
+            let Some(&split_index) = preferences[guest].get(next_choices[guest]) else {
+                continue;
+            };
+            next_choices[guest] += 1;
+
+            let split = &input.splits[split_index];
+            let rank = |guest: usize| host_rank(split, input.users[guest], guest);
+            match holders[split_index] {
+                Some(holder) if rank(holder) < rank(guest) => free_guests.push(guest),
+                Some(holder) => {
+                    holders[split_index] = Some(guest);
+                    free_guests.push(holder);
+                }
+                None => holders[split_index] = Some(guest),
+            }
+        }
+
+        let mut leases: Vec<(usize, usize)> = holders
+            .iter()
+            .enumerate()
+            .filter_map(|(split_index, holder)| holder.map(|guest| (guest, split_index)))
+            .collect();
+        leases.sort_unstable();
+
+        let mut output = PeriodOutput::default();
+        for (guest, split_index) in leases {
+            output.lease(&input.splits[split_index], input.users[guest], &input);
+        }
+        output
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{
+    contract::Contract,
+    rental_space::Split,
+    user::{User, UserId},
+    Generator,
+};
+
+use {
+    chrono::NaiveDate,
+    serde::{Deserialize, Serialize},
+};
+
+/// Algorithm matching guests with the splits available at the start of a
+/// period. The [`MatchingEngine`](super::MatchingEngine) runs the strategy
+/// once per period, keeps track of the workstations leased by the generated
+/// contracts and offers the guests left unmatched again in the next period.
+/// Markets of different regions are matched concurrently with the same
+/// strategy, which must therefore be [`Sync`].
+pub trait MatchingStrategy: Sync {
+    /// Whether rental spaces may be divided between several guests. If not,
+    /// only rental spaces that are entirely free are offered.
+    fn allows_subsplit(&self) -> bool;
+
+    /// Matches the guests of `input` with its splits. Every guest must get at
+    /// most one contract, and the contracts of a split must not lease more
+    /// workstations than it has.
+    fn match_period(&self, input: PeriodInput) -> PeriodOutput;
+}
+
+/// Guests and splits to match at the start of a period.
+pub struct PeriodInput<'a> {
+    /// Start date of the period, and of the generated contracts.
+    pub start_date: NaiveDate,
+    /// Guests to match, in the order set by the
+    /// [`OrderingPolicy`](super::OrderingPolicy). All of them have a
+    /// workspace request.
+    pub users: Vec<&'a User>,
+    /// The free part of every rental space that can be leased.
+    pub splits: Vec<Split>,
+    /// Generator to use for the identifiers of new splits and contracts.
+    pub generator: &'a Generator,
+}
+
+/// Contracts generated in a period, along with notes on how they were found.
+#[derive(Default)]
+pub struct PeriodOutput {
+    pub contracts: Vec<Contract>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Note left by a strategy about a decision it made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<UserId>,
+    pub message: String,
+}
+
+impl PeriodOutput {
+    /// Leases the whole `piece` to `user` for the term of their request, and
+    /// notes when the guest gets more workstations than they want.
+    pub fn lease(&mut self, piece: &Split, user: &User, input: &PeriodInput) {
+        let request = user.workspace_request.as_ref().unwrap();
+        let max_nb_workstations = *request.nb_workstations_range().end();
+        if piece.nb_workstations > max_nb_workstations {
+            self.diagnostics.push(Diagnostic {
+                user_id: Some(user.id().clone()),
+                message: format!(
+                    "Leased {} workstations instead of the {} requested to respect the split constraints",
+                    piece.nb_workstations, max_nb_workstations
+                ),
+            });
+        }
+        self.contracts.push(Contract::new(
+            piece,
+            user.id().clone(),
+            &request.budget,
+            input.start_date,
+            request.term_months,
+            input.generator,
+        ));
+    }
+}
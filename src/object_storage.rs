@@ -1,16 +1,43 @@
 use crate::{
+    amenity::Amenity,
+    contract::{Contract, ContractAction},
+    location::{Address, Coordinates},
+    matching::{MatchingEngine, MatchingHorizon, Matchings},
+    pricing::{Currency, Price},
     rental_space::{AddRentalSpaceRequest, RentalSpace},
     user::{AddUserRequest, User, WorkspaceRequest},
+    waitlist::{Waitlist, WaitlistEntry, WaitlistPosition},
+    Generator,
 };
 
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
 
 #[derive(Default)]
 pub struct ObjectStorage {
     users: HashMap<String, User>,
     rental_spaces: HashMap<String, RentalSpace>,
+    contracts: HashMap<String, Contract>,
+    waitlist: Waitlist,
+    generator: Generator,
+}
+
+#[derive(Debug)]
+pub struct ObjectNotFound {
+    pub kind: &'static str,
+    pub id: String,
+}
+
+impl fmt::Display for ObjectNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} with id {} not found", self.kind, self.id)
+    }
 }
 
+impl std::error::Error for ObjectNotFound {}
+
 impl ObjectStorage {
     pub fn new() -> Self {
         Self {
@@ -18,6 +45,17 @@ impl ObjectStorage {
         }
     }
 
+    /// Uses `generator` for the identifiers and timestamps of the objects
+    /// created from requests.
+    pub fn with_generator(mut self, generator: Generator) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    pub fn generator(&self) -> &Generator {
+        &self.generator
+    }
+
     pub fn add_user(&mut self, user: User) {
         self.users.insert(user.id_value().to_owned(), user);
     }
@@ -27,6 +65,81 @@ impl ObjectStorage {
             .insert(rental_space.id_value().to_owned(), rental_space);
     }
 
+    pub fn create_user(&mut self, request: AddUserRequest) -> anyhow::Result<&User> {
+        let user = User::new(request, &self.generator)?;
+        let id = user.id_value().to_owned();
+        self.add_user(user);
+        Ok(&self.users[&id])
+    }
+
+    pub fn create_rental_space(
+        &mut self,
+        request: AddRentalSpaceRequest,
+    ) -> anyhow::Result<&RentalSpace> {
+        let owner = self
+            .get_user(&request.owner_id)
+            .ok_or_else(|| ObjectNotFound {
+                kind: "User",
+                id: request.owner_id.clone(),
+            })?;
+        let rental_space = RentalSpace::new(request, owner.id().clone(), &self.generator)?;
+        let id = rental_space.id_value().to_owned();
+        self.add_rental_space(rental_space);
+        self.place_waitlisted_users();
+        Ok(&self.rental_spaces[&id])
+    }
+
+    pub fn update_user(&mut self, id: &str, request: AddUserRequest) -> anyhow::Result<&User> {
+        let user = self.users.get_mut(id).ok_or_else(|| ObjectNotFound {
+            kind: "User",
+            id: id.to_owned(),
+        })?;
+        user.update(request)?;
+        if user.workspace_request.is_none() {
+            self.waitlist.remove(user.id());
+        }
+        Ok(user)
+    }
+
+    pub fn update_rental_space(
+        &mut self,
+        id: &str,
+        request: AddRentalSpaceRequest,
+    ) -> anyhow::Result<&RentalSpace> {
+        let owner_id = self
+            .get_user(&request.owner_id)
+            .ok_or_else(|| ObjectNotFound {
+                kind: "User",
+                id: request.owner_id.clone(),
+            })?
+            .id()
+            .clone();
+        let rental_space = self
+            .rental_spaces
+            .get_mut(id)
+            .ok_or_else(|| ObjectNotFound {
+                kind: "Rental space",
+                id: id.to_owned(),
+            })?;
+        rental_space.update(request, owner_id)?;
+        Ok(rental_space)
+    }
+
+    pub fn remove_user(&mut self, id: &str) -> Option<User> {
+        let user = self.users.remove(id)?;
+        self.waitlist.remove(user.id());
+        Some(user)
+    }
+
+    pub fn remove_rental_space(&mut self, id: &str) -> Option<RentalSpace> {
+        self.rental_spaces.remove(id)
+    }
+
+    pub fn add_contract(&mut self, contract: Contract) {
+        self.contracts
+            .insert(contract.id_value().to_owned(), contract);
+    }
+
     pub fn get_user(&self, id: &str) -> Option<&User> {
         self.users.get(id)
     }
@@ -35,12 +148,126 @@ impl ObjectStorage {
         self.rental_spaces.get(id)
     }
 
+    pub fn get_contract(&self, id: &str) -> Option<&Contract> {
+        self.contracts.get(id)
+    }
+
+    /// Applies `action` to the contract, timestamped with the current time of
+    /// the generator. Waitlisted guests are placed if the contract no longer
+    /// holds its workstations.
+    pub fn transition_contract(
+        &mut self,
+        id: &str,
+        action: ContractAction,
+    ) -> anyhow::Result<&Contract> {
+        let contract = self.contracts.get_mut(id).ok_or_else(|| ObjectNotFound {
+            kind: "Contract",
+            id: id.to_owned(),
+        })?;
+        contract.transition(action, self.generator.now())?;
+        if !contract.status().holds_workstations() {
+            self.place_waitlisted_users();
+        }
+        Ok(&self.contracts[id])
+    }
+
+    /// Adds the contracts of `matchings` and updates the waitlist with the
+    /// users it left unmatched. Only the matchings of an incremental
+    /// [`MatchingEngine`] should be stored, as the other ones may lease
+    /// workstations that stored contracts already lease.
+    pub fn store_matchings(&mut self, matchings: &Matchings) {
+        for contract in matchings.contracts() {
+            self.add_contract(contract.clone());
+        }
+        self.waitlist.update(matchings, self.generator.now());
+    }
+
+    /// Offers the workstations that are free today to the waitlisted users,
+    /// in the order of the queue, keeping the stored contracts. Rental spaces
+    /// may be split, as with the greedy matching with splitting.
+    pub fn place_waitlisted_users(&mut self) -> Option<Matchings> {
+        if self.waitlist.is_empty() {
+            return None;
+        }
+        let matchings = MatchingEngine::new(self)
+            .with_horizon(MatchingHorizon::new(self.generator.now().date_naive(), 1))
+            .with_incremental(true)
+            .with_waitlist(true)
+            .get_greedy_matchings(true);
+        self.store_matchings(&matchings);
+        Some(matchings)
+    }
+
+    pub fn waitlist(&self) -> &Waitlist {
+        &self.waitlist
+    }
+
+    pub fn add_waitlist_entry(&mut self, entry: WaitlistEntry) {
+        self.waitlist.push(entry);
+    }
+
+    /// Returns the users of the waitlist, in the order of the queue.
+    pub fn waitlisted_users(&self) -> Vec<&User> {
+        self.waitlist
+            .entries()
+            .iter()
+            .filter_map(|entry| self.users.get(entry.user_id.value()))
+            .collect()
+    }
+
+    /// Returns every entry of the waitlist with its position.
+    pub fn waitlist_positions(&self) -> Vec<WaitlistPosition<'_>> {
+        self.waitlist
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| self.waitlist_position(index + 1, entry))
+            .collect()
+    }
+
+    /// Returns the entry of the user in the waitlist with its position.
+    pub fn get_waitlist_position(&self, user_id: &str) -> Option<WaitlistPosition<'_>> {
+        let user = self.get_user(user_id)?;
+        let position = self.waitlist.position(user.id())?;
+        Some(self.waitlist_position(position, &self.waitlist.entries()[position - 1]))
+    }
+
+    fn waitlist_position<'a>(
+        &'a self,
+        position: usize,
+        entry: &'a WaitlistEntry,
+    ) -> WaitlistPosition<'a> {
+        WaitlistPosition {
+            position,
+            entry,
+            workspace_request: self
+                .users
+                .get(entry.user_id.value())
+                .and_then(|user| user.workspace_request.as_ref()),
+        }
+    }
+
+    /// Returns all users ordered by creation time, then by id.
     pub fn users(&self) -> Vec<&User> {
-        self.users.values().collect()
+        let mut users: Vec<&User> = self.users.values().collect();
+        users.sort_by(|a, b| (a.created_at(), a.id_value()).cmp(&(b.created_at(), b.id_value())));
+        users
     }
 
+    /// Returns all rental spaces ordered by creation time, then by id.
     pub fn rental_spaces(&self) -> Vec<&RentalSpace> {
-        self.rental_spaces.values().collect()
+        let mut rental_spaces: Vec<&RentalSpace> = self.rental_spaces.values().collect();
+        rental_spaces
+            .sort_by(|a, b| (a.created_at(), a.id_value()).cmp(&(b.created_at(), b.id_value())));
+        rental_spaces
+    }
+
+    /// Returns all contracts ordered by creation time, then by id.
+    pub fn contracts(&self) -> Vec<&Contract> {
+        let mut contracts: Vec<&Contract> = self.contracts.values().collect();
+        contracts
+            .sort_by(|a, b| (a.created_at(), a.id_value()).cmp(&(b.created_at(), b.id_value())));
+        contracts
     }
 
     pub fn merge(&mut self, other: ObjectStorage) {
@@ -51,11 +278,22 @@ impl ObjectStorage {
         for (key, value) in other.rental_spaces {
             self.rental_spaces.insert(key, value);
         }
+
+        for (key, value) in other.contracts {
+            self.contracts.insert(key, value);
+        }
+
+        for entry in other.waitlist.entries() {
+            if self.waitlist.position(&entry.user_id).is_none() {
+                self.waitlist.push(entry.clone());
+            }
+        }
     }
 }
 
 pub fn example_storage() -> ObjectStorage {
     let mut storage = ObjectStorage::new();
+    let generator = storage.generator().clone();
 
     let mut hosts = vec![];
 
@@ -66,7 +304,7 @@ pub fn example_storage() -> ObjectStorage {
             workspace_request: None,
         };
 
-        let user = User::new(add_user_request).unwrap();
+        let user = User::new(add_user_request, &generator).unwrap();
         hosts.push(user.clone());
         storage.add_user(user);
     }
@@ -75,11 +313,23 @@ pub fn example_storage() -> ObjectStorage {
         for nb_workstations in [160, 160, 160] {
             let add_rental_space_request = AddRentalSpaceRequest {
                 name: "Rental Space".to_string(),
-                address: "123 Main St".to_string(),
+                address: Address {
+                    street: "123 Main St".to_string(),
+                    postal_code: "10001".to_string(),
+                    city: "Cityville".to_string(),
+                    country: "Country".to_string(),
+                    coordinates: Some(Coordinates {
+                        latitude: 48.8566,
+                        longitude: 2.3522,
+                    }),
+                },
                 surface,
                 nb_workstations,
                 price_per_workstation: 400,
+                currency: Currency::default(),
                 owner_id: "usr-123".to_string(),
+                region: None,
+                amenities: BTreeSet::from([Amenity::MeetingRooms, Amenity::Kitchen]),
             };
 
             use rand::seq::SliceRandom;
@@ -87,6 +337,7 @@ pub fn example_storage() -> ObjectStorage {
             let rental_space = RentalSpace::new(
                 add_rental_space_request,
                 hosts.choose(&mut rand::thread_rng()).unwrap().id().clone(),
+                &generator,
             );
             if let Ok(rental_space) = rental_space {
                 storage.add_rental_space(rental_space);
@@ -100,11 +351,20 @@ pub fn example_storage() -> ObjectStorage {
             last_name: "Doe".to_string(),
             workspace_request: Some(WorkspaceRequest {
                 nb_workstations,
-                budget: 1_000_000_000,
+                max_nb_workstations: None,
+                budget: Price::total_yearly(1_000_000_000, Currency::default()),
+                desired_start_date: None,
+                term_months: 12,
+                priority: 0,
+                region: None,
+                location: None,
+                required_amenities: BTreeSet::new(),
+                preferred_amenities: BTreeSet::new(),
+                renew: false,
             }),
         };
 
-        let user = User::new(add_user_request).unwrap();
+        let user = User::new(add_user_request, &generator).unwrap();
         storage.add_user(user);
     }
 
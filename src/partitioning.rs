@@ -0,0 +1,140 @@
+use crate::{
+    rental_space::{self, Split},
+    user::WorkspaceRequest,
+    Generator,
+};
+
+/// Division of a split into one piece per guest, plus the workstations left
+/// free.
+#[derive(Debug)]
+pub struct Partition {
+    pub pieces: Vec<Split>,
+    pub rest: Option<Split>,
+}
+
+/// Plans the pieces of `split` shared by all `requests`, in the same order as
+/// the requests, or returns `None` if they cannot share it.
+pub fn plan(
+    split: &Split,
+    requests: &[&WorkspaceRequest],
+    with_subsplit: bool,
+    generator: &Generator,
+) -> Option<Partition> {
+    partition(split, &layout(split, requests, with_subsplit)?, generator)
+}
+
+/// Divides `split` into pieces of the given sizes, the remaining workstations
+/// forming one more free piece. The surface is divided in proportion to the
+/// number of workstations.
+///
+/// Returns `None` if the sizes exceed the split, or if the split is divided and
+/// one of the pieces does not respect the workstation range or density.
+pub fn partition(split: &Split, sizes: &[u32], generator: &Generator) -> Option<Partition> {
+    let dimensions = dimensions(split, sizes)?;
+    let mut pieces: Vec<Split> = dimensions
+        .iter()
+        .map(|&(size, surface)| split.piece_with_surface(size, surface, generator))
+        .collect();
+    let rest = (pieces.len() > sizes.len()).then(|| pieces.pop().unwrap());
+    Some(Partition { pieces, rest })
+}
+
+/// Returns the number of workstations each request gets if all of them share
+/// the split, or `None` if they cannot share it.
+///
+/// Every piece must be at least as large as the minimal subsplit size, and the
+/// workstations left over must either form a valid split on their own or be
+/// absorbed by guests whose budget allows it.
+pub fn layout(
+    split: &Split,
+    requests: &[&WorkspaceRequest],
+    with_subsplit: bool,
+) -> Option<Vec<u32>> {
+    let whole_split = |request: &WorkspaceRequest| {
+        (split.nb_workstations >= request.nb_workstations
+            && request
+                .budget
+                .affords(split.nb_workstations, split.price_per_workstation))
+        .then(|| vec![split.nb_workstations])
+    };
+
+    let min_nb_workstations = match (with_subsplit, split.subsplit_min_nb_workstations()) {
+        (true, Some(min)) => min,
+        _ => {
+            return match requests {
+                [request] => whole_split(request),
+                _ => None,
+            };
+        }
+    };
+
+    let mut sizes: Vec<u32> = requests
+        .iter()
+        .map(|request| std::cmp::max(min_nb_workstations, request.nb_workstations))
+        .collect();
+
+    let affordable_sizes: Vec<u32> = requests
+        .iter()
+        .map(|request| {
+            request
+                .budget
+                .affordable_nb_workstations(split.price_per_workstation)
+        })
+        .collect();
+
+    if sizes
+        .iter()
+        .zip(&affordable_sizes)
+        .any(|(size, affordable)| size > affordable)
+    {
+        return None;
+    }
+
+    let total: u32 = sizes.iter().sum();
+    if total > split.nb_workstations {
+        return None;
+    }
+
+    let mut leftover = split.nb_workstations - total;
+    if leftover > 0 && leftover < min_nb_workstations {
+        for (size, affordable) in sizes.iter_mut().zip(affordable_sizes) {
+            let extra = std::cmp::min(leftover, affordable - *size);
+            *size += extra;
+            leftover -= extra;
+        }
+        if leftover > 0 {
+            return None;
+        }
+    }
+
+    dimensions(split, &sizes)?;
+    Some(sizes)
+}
+
+/// Returns the number of workstations and the surface of every piece, followed
+/// by the free piece if any workstations are left. Surfaces are rounded down,
+/// the free piece getting what is left.
+///
+/// Returns `None` if the sizes exceed the split, or if the split is divided and
+/// a piece does not respect the constraints of a rental space.
+fn dimensions(split: &Split, sizes: &[u32]) -> Option<Vec<(u32, u32)>> {
+    let nb_free_workstations = split.nb_workstations.checked_sub(sizes.iter().sum())?;
+
+    let mut dimensions: Vec<(u32, u32)> = sizes
+        .iter()
+        .map(|&size| (size, split.surface() * size / split.nb_workstations))
+        .collect();
+
+    if nb_free_workstations > 0 {
+        let free_surface =
+            split.surface() - dimensions.iter().map(|(_, surface)| surface).sum::<u32>();
+        dimensions.push((nb_free_workstations, free_surface));
+    }
+
+    let is_divided = dimensions.len() > 1;
+    (!is_divided
+        || dimensions
+            .iter()
+            .all(|&(size, surface)| rental_space::respects_constraints(size, surface)))
+    .then_some(dimensions)
+}
@@ -0,0 +1,474 @@
+use crate::{
+    contract::{Contract, ContractStatus, StatusChange},
+    location::Address,
+    object_storage::ObjectStorage,
+    pricing::{Currency, Price},
+    rental_space::RentalSpace,
+    user::User,
+    waitlist::WaitlistEntry,
+};
+
+use {
+    anyhow::Context,
+    chrono::{DateTime, Months, Utc},
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    serde_json::Value,
+    std::{
+        fs::{self, File},
+        io::{ErrorKind, Write},
+        path::{Path, PathBuf},
+    },
+    validator::Validate,
+};
+
+/// Version of the on-disk format. Bump it whenever the serialized
+/// representation of a stored object changes, and teach [`migrate`] how to
+/// upgrade the previous version.
+pub const SCHEMA_VERSION: u32 = 5;
+
+const USERS: &str = "users";
+const RENTAL_SPACES: &str = "rental_spaces";
+const CONTRACTS: &str = "contracts";
+const WAITLIST: &str = "waitlist";
+
+/// Stores the content of an [`ObjectStorage`] as JSON files in a data
+/// directory, one file per kind of object.
+pub struct JsonFileStore {
+    data_dir: PathBuf,
+}
+
+#[derive(Serialize)]
+struct Snapshot<'a, T> {
+    schema_version: u32,
+    items: Vec<&'a T>,
+}
+
+#[derive(Deserialize)]
+struct RawSnapshot {
+    schema_version: u32,
+    items: Vec<Value>,
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+    schema_version: u32,
+    users: Vec<&'a User>,
+    rental_spaces: Vec<&'a RentalSpace>,
+    contracts: Vec<&'a Contract>,
+    waitlist: &'a [WaitlistEntry],
+}
+
+#[derive(Deserialize)]
+struct RawExport {
+    schema_version: u32,
+    #[serde(default)]
+    users: Vec<Value>,
+    #[serde(default)]
+    rental_spaces: Vec<Value>,
+    #[serde(default)]
+    contracts: Vec<Value>,
+    #[serde(default)]
+    waitlist: Vec<Value>,
+}
+
+impl JsonFileStore {
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            data_dir: data_dir.into(),
+        }
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Loads the storage from the data directory. Missing files are treated
+    /// as empty, so loading from a fresh directory returns an empty storage.
+    pub fn load(&self) -> anyhow::Result<ObjectStorage> {
+        let mut storage = ObjectStorage::new();
+
+        for user in self.read_items::<User>(USERS)? {
+            storage.add_user(user);
+        }
+
+        for rental_space in self.read_items::<RentalSpace>(RENTAL_SPACES)? {
+            storage.add_rental_space(rental_space);
+        }
+
+        for contract in self.read_items::<Contract>(CONTRACTS)? {
+            storage.add_contract(contract);
+        }
+
+        for entry in self.read_items::<WaitlistEntry>(WAITLIST)? {
+            storage.add_waitlist_entry(entry);
+        }
+
+        Ok(storage)
+    }
+
+    /// Saves the storage to the data directory. Every file is replaced
+    /// atomically, so an interrupted save never leaves a truncated file.
+    pub fn save(&self, storage: &ObjectStorage) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.data_dir).with_context(|| {
+            format!(
+                "Failed to create data directory {}",
+                self.data_dir.display()
+            )
+        })?;
+
+        self.write_items(USERS, storage.users())?;
+        self.write_items(RENTAL_SPACES, storage.rental_spaces())?;
+        self.write_items(CONTRACTS, storage.contracts())?;
+        self.write_items(WAITLIST, storage.waitlist().entries().iter().collect())?;
+
+        Ok(())
+    }
+
+    fn read_items<T: DeserializeOwned + Validate>(&self, kind: &str) -> anyhow::Result<Vec<T>> {
+        let path = self.data_dir.join(format!("{}.json", kind));
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        let snapshot: RawSnapshot = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        parse_items(kind, snapshot.schema_version, snapshot.items)
+            .with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    fn write_items<T: Serialize>(&self, kind: &str, items: Vec<&T>) -> anyhow::Result<()> {
+        let snapshot = Snapshot {
+            schema_version: SCHEMA_VERSION,
+            items,
+        };
+        let content = serde_json::to_string_pretty(&snapshot)?;
+
+        let path = self.data_dir.join(format!("{}.json", kind));
+        let tmp_path = self.data_dir.join(format!(".{}.json.tmp", kind));
+
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Serializes the whole storage into a single JSON document, which can be
+/// loaded back with [`import`].
+pub fn export(storage: &ObjectStorage) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(&Export {
+        schema_version: SCHEMA_VERSION,
+        users: storage.users(),
+        rental_spaces: storage.rental_spaces(),
+        contracts: storage.contracts(),
+        waitlist: storage.waitlist().entries(),
+    })?)
+}
+
+/// Parses a document produced by [`export`], possibly by an older version of
+/// the program.
+pub fn import(document: &str) -> anyhow::Result<ObjectStorage> {
+    let export: RawExport = serde_json::from_str(document)?;
+    let mut storage = ObjectStorage::new();
+
+    for user in parse_items::<User>(USERS, export.schema_version, export.users)? {
+        storage.add_user(user);
+    }
+
+    for rental_space in
+        parse_items::<RentalSpace>(RENTAL_SPACES, export.schema_version, export.rental_spaces)?
+    {
+        storage.add_rental_space(rental_space);
+    }
+
+    for contract in parse_items::<Contract>(CONTRACTS, export.schema_version, export.contracts)? {
+        storage.add_contract(contract);
+    }
+
+    for entry in parse_items::<WaitlistEntry>(WAITLIST, export.schema_version, export.waitlist)? {
+        storage.add_waitlist_entry(entry);
+    }
+
+    Ok(storage)
+}
+
+/// Parses stored items of the given kind, upgrading them to the latest
+/// schema version, and validates them as when they were created.
+fn parse_items<T: DeserializeOwned + Validate>(
+    kind: &str,
+    schema_version: u32,
+    items: Vec<Value>,
+) -> anyhow::Result<Vec<T>> {
+    if schema_version > SCHEMA_VERSION {
+        anyhow::bail!(
+            "Schema version {} is not supported, the latest supported version is {}",
+            schema_version,
+            SCHEMA_VERSION
+        );
+    }
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let item: T = serde_json::from_value(migrate(kind, schema_version, item)?)?;
+            item.validate()
+                .with_context(|| format!("Invalid item {} of {}", index, kind))?;
+            Ok(item)
+        })
+        .collect()
+}
+
+/// Upgrades a stored item of the given kind from `schema_version` to
+/// [`SCHEMA_VERSION`].
+fn migrate(kind: &str, schema_version: u32, item: Value) -> anyhow::Result<Value> {
+    match schema_version {
+        SCHEMA_VERSION => Ok(item),
+        1 => migrate(kind, 2, migrate_v1_to_v2(kind, item)?),
+        2 => migrate(kind, 3, migrate_v2_to_v3(kind, item)?),
+        3 => migrate(kind, 4, migrate_v3_to_v4(kind, item)?),
+        4 => migrate(kind, 5, migrate_v4_to_v5(kind, item)?),
+        _ => anyhow::bail!(
+            "No migration from schema version {} is known for {}",
+            schema_version,
+            kind
+        ),
+    }
+}
+
+/// Version 1 contracts have no dates: they are assumed to start on the day
+/// they were created and to last one year.
+fn migrate_v1_to_v2(kind: &str, mut item: Value) -> anyhow::Result<Value> {
+    if kind != CONTRACTS {
+        return Ok(item);
+    }
+
+    let created_at: DateTime<Utc> = serde_json::from_value(item["created_at"].clone())?;
+    let start_date = created_at.date_naive();
+    let term_months = 12;
+    item["start_date"] = serde_json::to_value(start_date)?;
+    item["end_date"] = serde_json::to_value(start_date + Months::new(term_months))?;
+    item["term_months"] = term_months.into();
+    Ok(item)
+}
+
+/// Version 2 rental spaces have a free-form address. It is parsed as
+/// `street, [postal code] city, country`, and kept as the street when it does
+/// not have this shape.
+fn migrate_v2_to_v3(kind: &str, mut item: Value) -> anyhow::Result<Value> {
+    if kind != RENTAL_SPACES {
+        return Ok(item);
+    }
+
+    let address = item["address"]
+        .as_str()
+        .context("Rental space address is not a string")?;
+    item["address"] = serde_json::to_value(parse_address(address))?;
+    Ok(item)
+}
+
+fn parse_address(address: &str) -> Address {
+    let parts: Vec<&str> = address.split(',').map(str::trim).collect();
+    let unparsed = Address {
+        street: address.trim().to_owned(),
+        postal_code: String::new(),
+        city: String::new(),
+        country: String::new(),
+        coordinates: None,
+    };
+
+    let [street @ .., locality, country] = parts.as_slice() else {
+        return unparsed;
+    };
+    if street.is_empty() {
+        return unparsed;
+    }
+
+    let (postal_code, city) = match locality.split_once(' ') {
+        Some((postal_code, city)) if postal_code.chars().any(|c| c.is_ascii_digit()) => {
+            (postal_code, city.trim())
+        }
+        _ => ("", *locality),
+    };
+    Address {
+        street: street.join(", "),
+        postal_code: postal_code.to_owned(),
+        city: city.to_owned(),
+        country: country.to_string(),
+        coordinates: None,
+    }
+}
+
+/// Version 3 budgets are plain numbers, the total yearly budget in euros.
+fn migrate_v3_to_v4(kind: &str, mut item: Value) -> anyhow::Result<Value> {
+    if kind != USERS {
+        return Ok(item);
+    }
+
+    if let Some(budget) = item.pointer_mut("/workspace_request/budget") {
+        let amount: u32 = serde_json::from_value(budget.clone())?;
+        *budget = serde_json::to_value(Price::total_yearly(amount, Currency::default()))?;
+    }
+    Ok(item)
+}
+
+/// Version 4 contracts have no status: they are considered active since they
+/// were created.
+fn migrate_v4_to_v5(kind: &str, mut item: Value) -> anyhow::Result<Value> {
+    if kind != CONTRACTS {
+        return Ok(item);
+    }
+
+    let status = ContractStatus::Active;
+    item["status"] = serde_json::to_value(status)?;
+    item["history"] = serde_json::to_value([StatusChange {
+        status,
+        by: None,
+        at: serde_json::from_value(item["created_at"].clone())?,
+    }])?;
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono::NaiveDate};
+
+    #[test]
+    fn loads_a_v1_snapshot_through_every_migration() {
+        let store = JsonFileStore::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/v1"));
+        let storage = store.load().unwrap();
+
+        assert_eq!(storage.users().len(), 2);
+        let guest = storage
+            .get_user("usr-2b4c6e80-3d5f-4a71-8b93-1d2e3f405b02")
+            .unwrap();
+        let request = guest.workspace_request.as_ref().unwrap();
+        assert_eq!(
+            request.budget,
+            Price::total_yearly(24000, Currency::default())
+        );
+        assert_eq!(request.nb_workstations_range(), 40..=40);
+        assert_eq!(request.term_months, 12);
+        assert_eq!(request.desired_start_date, None);
+        assert_eq!(request.priority, 0);
+        assert_eq!(request.region, None);
+        assert!(request.location.is_none());
+        assert!(request.required_amenities.is_empty());
+        assert!(request.preferred_amenities.is_empty());
+        assert!(!request.renew);
+
+        let rental_spaces = storage.rental_spaces();
+        assert_eq!(rental_spaces.len(), 1);
+        assert_eq!(
+            rental_spaces[0].address(),
+            &Address {
+                street: "123 Main St".to_owned(),
+                postal_code: "10001".to_owned(),
+                city: "Cityville".to_owned(),
+                country: "Country".to_owned(),
+                coordinates: None,
+            }
+        );
+
+        let contracts = storage.contracts();
+        assert_eq!(contracts.len(), 1);
+        let contract = contracts[0];
+        assert_eq!(
+            contract.start_date(),
+            NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()
+        );
+        assert_eq!(
+            contract.end_date(),
+            NaiveDate::from_ymd_opt(2025, 3, 15).unwrap()
+        );
+        assert_eq!(contract.term_months(), 12);
+        assert_eq!(contract.status(), ContractStatus::Active);
+        assert_eq!(contract.history().len(), 1);
+        assert_eq!(contract.history()[0].status, ContractStatus::Active);
+        assert!(storage.waitlist().is_empty());
+    }
+
+    fn fixture_export() -> Value {
+        let store = JsonFileStore::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/v1"));
+        serde_json::from_str(&export(&store.load().unwrap()).unwrap()).unwrap()
+    }
+
+    fn assert_rejected(document: Value) {
+        let Err(error) = import(&document.to_string()) else {
+            panic!("the document should be rejected");
+        };
+        assert!(error
+            .downcast_ref::<validator::ValidationErrors>()
+            .is_some());
+    }
+
+    #[test]
+    fn imports_an_export() {
+        let storage = import(&fixture_export().to_string()).unwrap();
+
+        assert_eq!(storage.users().len(), 2);
+        assert_eq!(storage.rental_spaces().len(), 1);
+        assert_eq!(storage.contracts().len(), 1);
+    }
+
+    /// Returns the objects of the v1 fixture as a single v1 document.
+    fn v1_export() -> Value {
+        let items = |kind: &str| {
+            let path = format!(
+                "{}/tests/fixtures/v1/{}.json",
+                env!("CARGO_MANIFEST_DIR"),
+                kind
+            );
+            let snapshot: RawSnapshot =
+                serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            snapshot.items
+        };
+        serde_json::json!({
+            "schema_version": 1,
+            "users": items(USERS),
+            "rental_spaces": items(RENTAL_SPACES),
+            "contracts": items(CONTRACTS),
+        })
+    }
+
+    #[test]
+    fn validates_imported_objects_after_migrating_them() {
+        let storage = import(&v1_export().to_string()).unwrap();
+        assert_eq!(storage.rental_spaces().len(), 1);
+
+        let mut document = v1_export();
+        document["rental_spaces"][0]["price_per_workstation"] = 200.into();
+        assert_rejected(document);
+    }
+
+    #[test]
+    fn rejects_an_imported_request_without_a_term() {
+        let mut document = fixture_export();
+        document["users"][1]["workspace_request"]["term_months"] = 0.into();
+
+        assert_rejected(document);
+    }
+
+    #[test]
+    fn rejects_an_imported_office_without_workstations() {
+        let mut document = fixture_export();
+        document["rental_spaces"][0]["nb_workstations"] = 0.into();
+
+        assert_rejected(document);
+    }
+
+    #[test]
+    fn rejects_an_imported_contract_without_a_term() {
+        let mut document = fixture_export();
+        document["contracts"][0]["term_months"] = 0.into();
+
+        assert_rejected(document);
+    }
+}
@@ -0,0 +1,197 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::fmt,
+    validator::{Validate, ValidationError},
+};
+
+/// ISO 4217 code of a currency, e.g. `EUR`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Currency {
+    code: String,
+}
+
+/// Whether a price is for all the workstations or for each of them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceBasis {
+    #[default]
+    Total,
+    PerWorkstation,
+}
+
+/// Period a price is paid for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PricePeriod {
+    Month,
+    #[default]
+    Year,
+}
+
+/// Amount paid for some workstations over a period, in a currency.
+///
+/// Prices are deserialized either from an object or from a plain number, a
+/// total yearly price in the default currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(from = "PriceRepr")]
+pub struct Price {
+    pub amount: u32,
+    pub basis: PriceBasis,
+    pub period: PricePeriod,
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Currency,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PriceRepr {
+    Amount(u32),
+    Price {
+        amount: u32,
+        #[serde(default)]
+        basis: PriceBasis,
+        #[serde(default)]
+        period: PricePeriod,
+        #[serde(default)]
+        currency: Currency,
+    },
+}
+
+impl Currency {
+    pub fn new(code: impl Into<String>) -> Self {
+        Self { code: code.into() }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::new("EUR")
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+impl From<PriceRepr> for Price {
+    fn from(repr: PriceRepr) -> Self {
+        match repr {
+            PriceRepr::Amount(amount) => Self::total_yearly(amount, Currency::default()),
+            PriceRepr::Price {
+                amount,
+                basis,
+                period,
+                currency,
+            } => Self {
+                amount,
+                basis,
+                period,
+                currency,
+            },
+        }
+    }
+}
+
+impl Price {
+    pub fn total_yearly(amount: u32, currency: Currency) -> Self {
+        Self {
+            amount,
+            basis: PriceBasis::Total,
+            period: PricePeriod::Year,
+            currency,
+        }
+    }
+
+    /// Returns the amount paid over a year.
+    pub fn yearly_amount(&self) -> u32 {
+        match self.period {
+            PricePeriod::Month => self.amount.saturating_mul(12),
+            PricePeriod::Year => self.amount,
+        }
+    }
+
+    /// Returns the amount paid over a year for `nb_workstations`
+    /// workstations.
+    pub fn yearly_total(&self, nb_workstations: u32) -> u32 {
+        match self.basis {
+            PriceBasis::Total => self.yearly_amount(),
+            PriceBasis::PerWorkstation => self.yearly_amount().saturating_mul(nb_workstations),
+        }
+    }
+
+    /// Returns the largest number of workstations this budget affords at
+    /// `price_per_workstation` per year, any number if they are free.
+    pub fn affordable_nb_workstations(&self, price_per_workstation: u32) -> u32 {
+        match self.basis {
+            PriceBasis::Total => self
+                .yearly_amount()
+                .checked_div(price_per_workstation)
+                .unwrap_or(u32::MAX),
+            PriceBasis::PerWorkstation if price_per_workstation <= self.yearly_amount() => u32::MAX,
+            PriceBasis::PerWorkstation => 0,
+        }
+    }
+
+    /// Returns whether this budget affords `nb_workstations` workstations at
+    /// `price_per_workstation` per year.
+    pub fn affords(&self, nb_workstations: u32, price_per_workstation: u32) -> bool {
+        nb_workstations <= self.affordable_nb_workstations(price_per_workstation)
+    }
+
+    /// Returns whether this budget affords one of several splits leased
+    /// entirely, given the lowest yearly price and the lowest yearly price
+    /// per workstation among them.
+    pub fn affords_any(&self, min_price: u32, min_price_per_workstation: u32) -> bool {
+        match self.basis {
+            PriceBasis::Total => min_price <= self.yearly_amount(),
+            PriceBasis::PerWorkstation => min_price_per_workstation <= self.yearly_amount(),
+        }
+    }
+
+    /// Expresses the price of `nb_workstations` workstations at
+    /// `price_per_workstation` per year in the same terms as this price,
+    /// rounded up to a whole amount.
+    pub fn quote(&self, nb_workstations: u32, price_per_workstation: u32) -> Price {
+        let yearly_amount = match self.basis {
+            PriceBasis::Total => nb_workstations.saturating_mul(price_per_workstation),
+            PriceBasis::PerWorkstation => price_per_workstation,
+        };
+        let amount = match self.period {
+            PricePeriod::Month => yearly_amount.div_ceil(12),
+            PricePeriod::Year => yearly_amount,
+        };
+        Self {
+            amount,
+            ..self.clone()
+        }
+    }
+}
+
+pub(crate) fn validate_currency(currency: &Currency) -> Result<(), ValidationError> {
+    if currency.code.len() == 3 && currency.code.bytes().all(|byte| byte.is_ascii_uppercase()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("currency")
+            .with_message("A currency must be a three-letter ISO 4217 code, e.g. EUR".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_workstations_are_always_affordable() {
+        let budget = Price::total_yearly(1000, Currency::new("EUR"));
+        assert_eq!(budget.affordable_nb_workstations(0), u32::MAX);
+        assert!(budget.affords(50, 0));
+        assert_eq!(budget.affordable_nb_workstations(300), 3);
+    }
+}
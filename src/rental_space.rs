@@ -1,64 +1,115 @@
-use super::{BaseFields, PrefixedUuid};
+use super::{BaseFields, Generator, PrefixedUuid};
 
 use {
-    serde::Deserialize,
-    std::fmt,
+    chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeSet, fmt, ops::RangeInclusive},
     validator::{Validate, ValidationError},
 };
 
-use crate::user::UserId;
+use crate::{
+    amenity::Amenity,
+    location::Address,
+    pricing::{validate_currency, Currency, Price},
+    user::UserId,
+};
+
+pub const MIN_NB_WORKSTATIONS: u32 = 40;
+pub const MAX_NB_WORKSTATIONS: u32 = 180;
 
+/// Rental spaces are validated as the requests creating them, including when
+/// they are loaded or imported, except for their address: older versions
+/// stored free-form addresses, which are kept as the street when they cannot
+/// be parsed.
+#[derive(Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_stored_workstation_density"))]
 pub struct RentalSpace {
+    #[serde(flatten)]
     base: BaseFields<RentalSpaceId>,
     name: String,
-    address: String,
+    address: Address,
     surface: u32,
+    #[validate(range(min = MIN_NB_WORKSTATIONS, max = MAX_NB_WORKSTATIONS))]
     nb_workstations: u32,
+    #[validate(range(min = 300, max = 800))]
     price_per_workstation: u32,
+    #[serde(default)]
+    #[validate(custom(function = "validate_currency"))]
+    currency: Currency,
     owner_id: UserId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    amenities: BTreeSet<Amenity>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 #[validate(schema(function = "validate_workstation_density"))]
 pub struct AddRentalSpaceRequest {
     pub name: String,
-    pub address: String,
+    #[validate(nested)]
+    pub address: Address,
     pub surface: u32,
-    #[validate(range(min = 40, max = 180))]
+    #[validate(range(min = MIN_NB_WORKSTATIONS, max = MAX_NB_WORKSTATIONS))]
     pub nb_workstations: u32,
+    /// Yearly price of a workstation.
     #[validate(range(min = 300, max = 800))]
     pub price_per_workstation: u32,
+    #[serde(default)]
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Currency,
     pub owner_id: String,
+    /// Only guests requesting this region are matched with the rental space.
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub amenities: BTreeSet<Amenity>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Split {
+    #[serde(flatten)]
     base: BaseFields<SplitId>,
     name: String,
-    address: String,
+    address: Address,
     surface: u32,
     pub nb_workstations: u32,
     pub price_per_workstation: u32,
+    #[serde(default)]
+    currency: Currency,
     pub parent_office_id: RentalSpaceId,
     pub owner_id: UserId,
+    /// Amenities of the parent rental space.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    amenities: BTreeSet<Amenity>,
 }
 
-#[derive(Clone, Eq, PartialEq, Hash)]
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct SplitId {
     value: String,
 }
 
-#[derive(Clone, Eq, PartialEq, Hash)]
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct RentalSpaceId {
     value: String,
 }
 
 impl PrefixedUuid for RentalSpaceId {
     const PREFIX: &'static str = "ofc";
+
+    fn from_value(value: String) -> Self {
+        Self { value }
+    }
 }
 
 impl PrefixedUuid for SplitId {
     const PREFIX: &'static str = "spl";
+
+    fn from_value(value: String) -> Self {
+        Self { value }
+    }
 }
 
 impl fmt::Debug for RentalSpaceId {
@@ -74,21 +125,44 @@ impl fmt::Debug for SplitId {
 }
 
 impl RentalSpace {
-    pub fn new(request: AddRentalSpaceRequest, owner_id: UserId) -> anyhow::Result<Self> {
+    pub fn new(
+        request: AddRentalSpaceRequest,
+        owner_id: UserId,
+        generator: &Generator,
+    ) -> anyhow::Result<Self> {
         request.validate()?;
         Ok(Self {
-            base: BaseFields::new(RentalSpaceId {
-                value: RentalSpaceId::generate(),
-            }),
+            base: BaseFields::new(generator),
             name: request.name,
             address: request.address,
             surface: request.surface,
             nb_workstations: request.nb_workstations,
             price_per_workstation: request.price_per_workstation,
+            currency: request.currency,
             owner_id,
+            region: request.region,
+            amenities: request.amenities,
         })
     }
 
+    pub fn update(
+        &mut self,
+        request: AddRentalSpaceRequest,
+        owner_id: UserId,
+    ) -> anyhow::Result<()> {
+        request.validate()?;
+        self.name = request.name;
+        self.address = request.address;
+        self.surface = request.surface;
+        self.nb_workstations = request.nb_workstations;
+        self.price_per_workstation = request.price_per_workstation;
+        self.currency = request.currency;
+        self.owner_id = owner_id;
+        self.region = request.region;
+        self.amenities = request.amenities;
+        Ok(())
+    }
+
     pub fn id(&self) -> &RentalSpaceId {
         &self.base.id
     }
@@ -96,6 +170,57 @@ impl RentalSpace {
     pub fn id_value(&self) -> &str {
         &self.id().value
     }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.base.created_at
+    }
+
+    pub fn owner_id(&self) -> &UserId {
+        &self.owner_id
+    }
+
+    pub fn nb_workstations(&self) -> u32 {
+        self.nb_workstations
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    pub fn amenities(&self) -> &BTreeSet<Amenity> {
+        &self.amenities
+    }
+
+    /// Returns a split covering the whole rental space.
+    pub fn split(&self, generator: &Generator) -> Split {
+        Split {
+            base: BaseFields::new(generator),
+            name: self.name.to_owned(),
+            address: self.address.clone(),
+            surface: self.surface,
+            nb_workstations: self.nb_workstations,
+            price_per_workstation: self.price_per_workstation,
+            currency: self.currency.clone(),
+            parent_office_id: self.base.id.clone(),
+            owner_id: self.owner_id.clone(),
+            amenities: self.amenities.clone(),
+        }
+    }
+
+    /// Returns the part of the rental space left free when
+    /// `nb_leased_workstations` workstations are leased, if any.
+    pub fn free_split(&self, nb_leased_workstations: u32, generator: &Generator) -> Option<Split> {
+        let nb_workstations = self.nb_workstations.checked_sub(nb_leased_workstations)?;
+        (nb_workstations > 0).then(|| self.split(generator).piece(nb_workstations, generator))
+    }
 }
 
 impl Split {
@@ -107,17 +232,50 @@ impl Split {
         &self.id().value
     }
 
+    /// Yearly price of the whole split.
     pub fn price(&self) -> u32 {
         self.nb_workstations * self.price_per_workstation
     }
 
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn surface(&self) -> u32 {
+        self.surface
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    pub fn amenities(&self) -> &BTreeSet<Amenity> {
+        &self.amenities
+    }
+
+    /// Returns whether the split can stand for `other` in any matching: both
+    /// have the same size, price, location and amenities.
+    pub fn is_equivalent(&self, other: &Split) -> bool {
+        self.nb_workstations == other.nb_workstations
+            && self.price_per_workstation == other.price_per_workstation
+            && self.surface == other.surface
+            && self.currency == other.currency
+            && self.address == other.address
+            && self.amenities == other.amenities
+    }
+
+    /// Returns the smallest number of workstations a piece of the split can
+    /// have while respecting the density constraint.
+    pub fn min_piece_nb_workstations(&self) -> u32 {
+        if self.nb_workstations * 8 < self.surface * 5 {
+            40
+        } else {
+            60
+        }
+    }
+
     pub fn subsplit_min_nb_workstations(&self) -> Option<u32> {
-        let min_nb_workstations_to_respect_density_constraint =
-            if self.nb_workstations * 8 < self.surface * 5 {
-                40
-            } else {
-                60
-            };
+        let min_nb_workstations_to_respect_density_constraint = self.min_piece_nb_workstations();
 
         if self.nb_workstations > 2 * min_nb_workstations_to_respect_density_constraint {
             Some(min_nb_workstations_to_respect_density_constraint)
@@ -136,56 +294,65 @@ impl Split {
             .is_some_and(|min| nb_workstations <= self.nb_workstations - min)
     }
 
-    pub fn subsplit(&self, nb_workstations: u32) -> Option<(Split, Split)> {
-        self.can_be_subsplit(nb_workstations).then(|| {
-            let nb_workstations = std::cmp::max(
-                self.subsplit_min_nb_workstations().unwrap(),
-                nb_workstations,
-            );
-            (
-                Split {
-                    base: BaseFields::new(SplitId {
-                        value: SplitId::generate(),
-                    }),
-                    name: self.name.to_owned(),
-                    address: self.address.to_owned(),
-                    surface: self.surface * nb_workstations / self.nb_workstations,
-                    nb_workstations,
-                    price_per_workstation: self.price_per_workstation,
-                    parent_office_id: self.parent_office_id.clone(),
-                    owner_id: self.owner_id.clone(),
-                },
-                Split {
-                    base: BaseFields::new(SplitId {
-                        value: SplitId::generate(),
-                    }),
-                    name: self.name.to_owned(),
-                    address: self.address.to_owned(),
-                    surface: self.surface * (self.nb_workstations - nb_workstations)
-                        / self.nb_workstations,
-                    nb_workstations: self.nb_workstations - nb_workstations,
-                    price_per_workstation: self.price_per_workstation,
-                    parent_office_id: self.parent_office_id.clone(),
-                    owner_id: self.owner_id.clone(),
-                },
-            )
-        })
+    /// Returns the number of workstations of the piece leased to a guest
+    /// wanting a number of workstations in `nb_workstations` for at most
+    /// `budget`: the largest affordable number in the range that leaves a
+    /// valid piece, raised to the minimal piece size if the range is below it.
+    pub fn subsplit_nb_workstations(
+        &self,
+        nb_workstations: RangeInclusive<u32>,
+        budget: &Price,
+    ) -> Option<u32> {
+        let min_piece = self.subsplit_min_nb_workstations()?;
+        let lowest = std::cmp::max(*nb_workstations.start(), min_piece);
+        let highest = std::cmp::max(*nb_workstations.end(), min_piece)
+            .min(self.nb_workstations - min_piece)
+            .min(budget.affordable_nb_workstations(self.price_per_workstation));
+        (lowest <= highest).then_some(highest)
+    }
+
+    /// Divides the split in the piece leased to a guest, see
+    /// [`Split::subsplit_nb_workstations`], and the rest.
+    pub fn subsplit(
+        &self,
+        nb_workstations: RangeInclusive<u32>,
+        budget: &Price,
+        generator: &Generator,
+    ) -> Option<(Split, Split)> {
+        let nb_workstations = self.subsplit_nb_workstations(nb_workstations, budget)?;
+        Some((
+            self.piece(nb_workstations, generator),
+            self.piece(self.nb_workstations - nb_workstations, generator),
+        ))
+    }
+
+    /// Returns a part of the split with `nb_workstations` workstations and a
+    /// proportional surface, without checking the split constraints.
+    pub fn piece(&self, nb_workstations: u32, generator: &Generator) -> Split {
+        self.piece_with_surface(
+            nb_workstations,
+            self.surface * nb_workstations / self.nb_workstations,
+            generator,
+        )
     }
-}
 
-impl From<&RentalSpace> for Split {
-    fn from(val: &RentalSpace) -> Self {
+    pub(crate) fn piece_with_surface(
+        &self,
+        nb_workstations: u32,
+        surface: u32,
+        generator: &Generator,
+    ) -> Split {
         Split {
-            base: BaseFields::new(SplitId {
-                value: SplitId::generate(),
-            }),
-            name: val.name.to_owned(),
-            address: val.address.to_owned(),
-            surface: val.surface,
-            nb_workstations: val.nb_workstations,
-            price_per_workstation: val.price_per_workstation,
-            parent_office_id: val.base.id.clone(),
-            owner_id: val.owner_id.clone(),
+            base: BaseFields::new(generator),
+            name: self.name.to_owned(),
+            address: self.address.clone(),
+            surface,
+            nb_workstations,
+            price_per_workstation: self.price_per_workstation,
+            currency: self.currency.clone(),
+            parent_office_id: self.parent_office_id.clone(),
+            owner_id: self.owner_id.clone(),
+            amenities: self.amenities.clone(),
         }
     }
 }
@@ -200,14 +367,20 @@ impl fmt::Debug for RentalSpace {
     surface: {},
     nb_workstations: {},
     price_per_workstation: {},
-    owner_id: {:?}",
+    currency: {:?},
+    owner_id: {:?},
+    region: {:?},
+    amenities: {:?}",
             self.name,
             self.base,
             self.address,
             self.surface,
             self.nb_workstations,
             self.price_per_workstation,
-            self.owner_id
+            self.currency,
+            self.owner_id,
+            self.region,
+            self.amenities
         )
     }
 }
@@ -215,14 +388,28 @@ impl fmt::Debug for RentalSpace {
 fn validate_workstation_density(
     add_rental_space_request: &AddRentalSpaceRequest,
 ) -> Result<(), ValidationError> {
+    check_workstation_density(
+        add_rental_space_request.nb_workstations,
+        add_rental_space_request.surface,
+    )
+}
+
+fn validate_stored_workstation_density(rental_space: &RentalSpace) -> Result<(), ValidationError> {
+    check_workstation_density(rental_space.nb_workstations, rental_space.surface)
+}
+
+/// Checks the constraints every rental space must respect: the range of
+/// workstations and the workstation density.
+pub fn respects_constraints(nb_workstations: u32, surface: u32) -> bool {
+    (MIN_NB_WORKSTATIONS..=MAX_NB_WORKSTATIONS).contains(&nb_workstations)
+        && check_workstation_density(nb_workstations, surface).is_ok()
+}
+
+fn check_workstation_density(nb_workstations: u32, surface: u32) -> Result<(), ValidationError> {
     let error = ValidationError::new("workstation_density");
-    if add_rental_space_request.nb_workstations < 60
-        && add_rental_space_request.nb_workstations * 8 > add_rental_space_request.surface * 5
-    {
+    if nb_workstations < 60 && nb_workstations * 8 > surface * 5 {
         return Err(error.with_message("Is is not allowed to have more than 5 workstations per 8 square meters if there are less than 60 workstations".into()));
-    } else if add_rental_space_request.nb_workstations >= 60
-        && add_rental_space_request.nb_workstations * 7 > add_rental_space_request.surface * 5
-    {
+    } else if nb_workstations >= 60 && nb_workstations * 7 > surface * 5 {
         return Err(error.with_message("Is is not allowed to have more than 5 workstations per 7 square meters if there are more than 60 workstations".into()));
     }
     Ok(())
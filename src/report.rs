@@ -0,0 +1,195 @@
+use crate::{
+    contract::Contract,
+    matching::{Matchings, UnmatchedUser},
+    object_storage::ObjectStorage,
+    pricing::Currency,
+    rental_space::RentalSpaceId,
+    user::UserId,
+};
+
+use {chrono::NaiveDate, serde::Serialize, std::collections::HashMap};
+
+/// Summary of a matching run, meant to explain its outcome to hosts and
+/// guests. Occupancy is measured at the start date of every period.
+#[derive(Debug, Serialize)]
+pub struct MatchingReport {
+    pub percentage_of_matched_users: i32,
+    /// Workstations leased at the start of every period, summed over periods.
+    pub total_workstations_leased: u32,
+    /// Workstations of all rental spaces, summed over periods.
+    pub total_workstations_available: u32,
+    pub periods: Vec<PeriodUtilization>,
+    pub rental_spaces: Vec<RentalSpaceUtilization>,
+    pub hosts: Vec<HostRevenue>,
+    pub unmatched_users: Vec<UnmatchedUser>,
+}
+
+#[derive(Serialize)]
+pub struct MatchingsWithReport<'a> {
+    pub matchings: &'a Matchings,
+    pub report: MatchingReport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeriodUtilization {
+    pub start_date: NaiveDate,
+    pub nb_workstations_leased: u32,
+    pub nb_workstations_available: u32,
+    pub occupancy_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RentalSpaceUtilization {
+    pub rental_space_id: RentalSpaceId,
+    pub host_id: UserId,
+    pub nb_workstations: u32,
+    /// Share of the workstations leased, averaged over periods.
+    pub occupancy_rate: f64,
+    pub revenue: Vec<Revenue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HostRevenue {
+    pub host_id: UserId,
+    /// Price of all contracts of the host over their whole term.
+    pub revenue: Vec<Revenue>,
+}
+
+/// Price of contracts over their whole term in one currency. Amounts in
+/// different currencies are never added up.
+#[derive(Debug, Clone, Serialize)]
+pub struct Revenue {
+    pub currency: Currency,
+    pub amount: u64,
+}
+
+impl MatchingReport {
+    pub fn new(storage: &ObjectStorage, matchings: &Matchings) -> Self {
+        let period_start_dates: Vec<NaiveDate> = matchings
+            .periods
+            .iter()
+            .map(|period| period.start_date)
+            .collect();
+
+        let mut contracts_by_rental_space: HashMap<&RentalSpaceId, Vec<&Contract>> = HashMap::new();
+        for contract in matchings.contracts() {
+            contracts_by_rental_space
+                .entry(contract.rental_space_id())
+                .or_default()
+                .push(contract);
+        }
+
+        let rental_spaces: Vec<RentalSpaceUtilization> = storage
+            .rental_spaces()
+            .into_iter()
+            .map(|rental_space| {
+                let contracts = contracts_by_rental_space
+                    .get(rental_space.id())
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+
+                let leased_rates: f64 = period_start_dates
+                    .iter()
+                    .map(|&date| {
+                        let nb_workstations_leased: u32 = contracts
+                            .iter()
+                            .filter(|contract| contract.is_active_on(date))
+                            .map(|contract| contract.nb_workstations())
+                            .sum();
+                        nb_workstations_leased as f64 / rental_space.nb_workstations() as f64
+                    })
+                    .sum();
+
+                RentalSpaceUtilization {
+                    rental_space_id: rental_space.id().clone(),
+                    host_id: rental_space.owner_id().clone(),
+                    nb_workstations: rental_space.nb_workstations(),
+                    occupancy_rate: rate(leased_rates, period_start_dates.len() as f64),
+                    revenue: contracts.iter().fold(Vec::new(), |mut revenue, contract| {
+                        add_revenue(&mut revenue, contract.currency(), contract.total_price());
+                        revenue
+                    }),
+                }
+            })
+            .collect();
+
+        let nb_workstations_available: u32 = rental_spaces
+            .iter()
+            .map(|rental_space| rental_space.nb_workstations)
+            .sum();
+
+        let periods: Vec<PeriodUtilization> = period_start_dates
+            .iter()
+            .map(|&start_date| {
+                let nb_workstations_leased = matchings
+                    .contracts()
+                    .filter(|contract| contract.is_active_on(start_date))
+                    .map(|contract| contract.nb_workstations())
+                    .sum();
+                PeriodUtilization {
+                    start_date,
+                    nb_workstations_leased,
+                    nb_workstations_available,
+                    occupancy_rate: rate(
+                        nb_workstations_leased as f64,
+                        nb_workstations_available as f64,
+                    ),
+                }
+            })
+            .collect();
+
+        let mut hosts: Vec<HostRevenue> = Vec::new();
+        let mut host_indices: HashMap<&UserId, usize> = HashMap::new();
+        for rental_space in &rental_spaces {
+            let index = *host_indices
+                .entry(&rental_space.host_id)
+                .or_insert_with(|| {
+                    hosts.push(HostRevenue {
+                        host_id: rental_space.host_id.clone(),
+                        revenue: Vec::new(),
+                    });
+                    hosts.len() - 1
+                });
+            for revenue in &rental_space.revenue {
+                add_revenue(&mut hosts[index].revenue, &revenue.currency, revenue.amount);
+            }
+        }
+
+        Self {
+            percentage_of_matched_users: matchings.percentage_of_matched_users,
+            total_workstations_leased: periods
+                .iter()
+                .map(|period| period.nb_workstations_leased)
+                .sum(),
+            total_workstations_available: nb_workstations_available
+                * period_start_dates.len() as u32,
+            periods,
+            rental_spaces,
+            hosts,
+            unmatched_users: matchings.unmatched_users.clone(),
+        }
+    }
+}
+
+/// Adds `amount` to the revenue in `currency`, after the other currencies if
+/// there is none yet.
+fn add_revenue(revenue: &mut Vec<Revenue>, currency: &Currency, amount: u64) {
+    match revenue
+        .iter_mut()
+        .find(|revenue| &revenue.currency == currency)
+    {
+        Some(revenue) => revenue.amount += amount,
+        None => revenue.push(Revenue {
+            currency: currency.clone(),
+            amount,
+        }),
+    }
+}
+
+fn rate(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
@@ -1,45 +1,123 @@
-use super::{BaseFields, PrefixedUuid};
+use super::{
+    amenity::Amenity, location::LocationPreference, pricing::Price, rental_space::Split,
+    BaseFields, Generator, PrefixedUuid,
+};
 
-use {serde::Deserialize, std::fmt};
+use {
+    chrono::{DateTime, NaiveDate, Utc},
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeSet, fmt, ops::RangeInclusive},
+    validator::{Validate, ValidationError},
+};
 
-#[derive(Clone)]
+/// Users are validated as the requests creating them, including when they
+/// are loaded or imported.
+#[derive(Clone, Serialize, Deserialize, Validate)]
 pub struct User {
+    #[serde(flatten)]
     base: BaseFields<UserId>,
     first_name: String,
     last_name: String,
+    #[validate(nested)]
     pub workspace_request: Option<WorkspaceRequest>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct AddUserRequest {
     pub first_name: String,
     pub last_name: String,
+    #[validate(nested)]
     pub workspace_request: Option<WorkspaceRequest>,
 }
 
-#[derive(Clone, Eq, PartialEq, Hash)]
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct UserId {
     value: String,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_nb_workstations_range"))]
 pub struct WorkspaceRequest {
+    /// The smallest number of workstations the guest accepts.
     pub nb_workstations: u32,
-    pub budget: u32,
+    /// The largest number of workstations the guest wants, `nb_workstations`
+    /// if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_nb_workstations: Option<u32>,
+    /// The highest price the guest accepts. The guest is only matched with
+    /// rental spaces priced in the same currency.
+    #[validate(nested)]
+    pub budget: Price,
+    /// The guest is not matched before this date if set.
+    #[serde(default)]
+    pub desired_start_date: Option<NaiveDate>,
+    #[serde(default = "default_term_months")]
+    #[validate(range(min = 1, max = MAX_TERM_MONTHS))]
+    pub term_months: u32,
+    /// Guests with a higher priority are matched first when matching by
+    /// priority, and have more chances to be drawn in a lottery.
+    #[serde(default)]
+    pub priority: u32,
+    /// The guest is only matched with rental spaces of this region. Guests
+    /// without a region are matched with rental spaces without a region.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// The guest is only matched with rental spaces in this location.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub location: Option<LocationPreference>,
+    /// The guest is only matched with rental spaces having all of these
+    /// amenities.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub required_amenities: BTreeSet<Amenity>,
+    /// Rental spaces having more of these amenities rank better when scoring.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub preferred_amenities: BTreeSet<Amenity>,
+    /// The guest keeps their workstations when their contract ends, if the
+    /// matching renews contracts and the renewed price fits the budget.
+    #[serde(default)]
+    pub renew: bool,
+}
+
+fn default_term_months() -> u32 {
+    12
+}
+
+/// Longest term a guest can request, in months.
+pub const MAX_TERM_MONTHS: u32 = 120;
+
+fn validate_nb_workstations_range(request: &WorkspaceRequest) -> Result<(), ValidationError> {
+    if request
+        .max_nb_workstations
+        .is_some_and(|max| max < request.nb_workstations)
+    {
+        return Err(ValidationError::new("nb_workstations_range").with_message(
+            "The maximum number of workstations cannot be lower than the minimum".into(),
+        ));
+    }
+    Ok(())
 }
 
 impl User {
-    pub fn new(request: AddUserRequest) -> anyhow::Result<Self> {
+    pub fn new(request: AddUserRequest, generator: &Generator) -> anyhow::Result<Self> {
+        request.validate()?;
         Ok(Self {
-            base: BaseFields::new(UserId {
-                value: UserId::generate(),
-            }),
+            base: BaseFields::new(generator),
             first_name: request.first_name,
             last_name: request.last_name,
             workspace_request: request.workspace_request,
         })
     }
 
+    pub fn update(&mut self, request: AddUserRequest) -> anyhow::Result<()> {
+        request.validate()?;
+        self.first_name = request.first_name;
+        self.last_name = request.last_name;
+        self.workspace_request = request.workspace_request;
+        Ok(())
+    }
+
     pub fn id(&self) -> &UserId {
         &self.base.id
     }
@@ -47,10 +125,51 @@ impl User {
     pub fn id_value(&self) -> &str {
         &self.id().value
     }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.base.created_at
+    }
+}
+
+impl WorkspaceRequest {
+    /// Returns the numbers of workstations the guest accepts.
+    pub fn nb_workstations_range(&self) -> RangeInclusive<u32> {
+        self.nb_workstations..=self.max_nb_workstations.unwrap_or(self.nb_workstations)
+    }
+
+    /// Returns whether the split is in the location preferred by the guest, is
+    /// priced in the currency of their budget and has all the amenities they
+    /// require.
+    pub fn accepts(&self, split: &Split) -> bool {
+        split.currency() == &self.budget.currency
+            && self
+                .location
+                .as_ref()
+                .is_none_or(|location| location.accepts(split.address()))
+            && self.required_amenities.is_subset(split.amenities())
+    }
+
+    /// Returns the number of amenities preferred by the guest that the split
+    /// lacks.
+    pub fn nb_missing_preferred_amenities(&self, split: &Split) -> usize {
+        self.preferred_amenities
+            .difference(split.amenities())
+            .count()
+    }
+}
+
+impl UserId {
+    pub fn value(&self) -> &str {
+        &self.value
+    }
 }
 
 impl PrefixedUuid for UserId {
     const PREFIX: &'static str = "usr";
+
+    fn from_value(value: String) -> Self {
+        Self { value }
+    }
 }
 
 impl fmt::Debug for UserId {
@@ -78,8 +197,18 @@ impl fmt::Debug for WorkspaceRequest {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "workspace_request: {{ nb_workstations: {}, budget: {} }}",
-            self.nb_workstations, self.budget
+            "workspace_request: {{ nb_workstations: {}, max_nb_workstations: {:?}, budget: {:?}, desired_start_date: {:?}, term_months: {}, priority: {}, region: {:?}, location: {:?}, required_amenities: {:?}, preferred_amenities: {:?}, renew: {} }}",
+            self.nb_workstations,
+            self.max_nb_workstations,
+            self.budget,
+            self.desired_start_date,
+            self.term_months,
+            self.priority,
+            self.region,
+            self.location,
+            self.required_amenities,
+            self.preferred_amenities,
+            self.renew
         )
     }
 }
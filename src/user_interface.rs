@@ -1,16 +1,33 @@
 use crate::{
-    matching::MatchingEngine,
+    matching::{
+        Algorithm, MatchingEngine, MatchingHorizon, OrderingPolicy, RenewalPolicy, ScoringWeights,
+        StableMatching,
+    },
     object_storage::{example_storage, ObjectStorage},
-    rental_space::{AddRentalSpaceRequest, RentalSpace},
-    user::{AddUserRequest, User},
+    persistence,
+    rental_space::AddRentalSpaceRequest,
+    report::MatchingReport,
+    user::AddUserRequest,
 };
 
-use inquire::{error::InquireError, Editor, Select};
+use {
+    clap::ValueEnum,
+    inquire::{error::InquireError, CustomType, Editor, Select},
+    serde::Serialize,
+    std::fmt,
+    validator::Validate,
+};
 
 pub struct Interface<'a> {
     storage: &'a mut ObjectStorage,
 }
 
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
 impl<'a> Interface<'a> {
     pub fn new(storage: &'a mut ObjectStorage) -> Self {
         Self { storage }
@@ -65,22 +82,35 @@ impl<'a> Interface<'a> {
     }
 
     pub fn print_objects(&self) {
-        let categories: Vec<&str> = vec!["users", "rental_spaces", "all"];
+        let categories: Vec<&str> = vec!["users", "rental_spaces", "waitlist", "all"];
 
         let ans: Result<&str, InquireError> = Select::new("Select category", categories).prompt();
 
+        let Some(format) = Self::inquire_output_format() else {
+            return;
+        };
+
         match ans {
             Ok("users") => {
-                self.print_users();
+                self.print_users(format);
             }
             Ok("rental_spaces") => {
-                self.print_rental_spaces();
+                self.print_rental_spaces(format);
+            }
+            Ok("waitlist") => {
+                Self::print(&self.storage.waitlist_positions(), format);
+            }
+            Ok("all") if matches!(format, OutputFormat::Json) => {
+                match persistence::export(self.storage) {
+                    Ok(json) => println!("{}\n", json),
+                    Err(e) => println!("Error serializing objects: {}", e),
+                }
             }
             Ok("all") => {
                 println!("Users:\n");
-                self.print_users();
+                self.print_users(format);
                 println!("Rental Spaces:\n");
-                self.print_rental_spaces();
+                self.print_rental_spaces(format);
             }
             Ok(_) => println!("Invalid category"),
             Err(_) => println!("There was an error, please try again"),
@@ -102,41 +132,236 @@ impl<'a> Interface<'a> {
             }
         };
 
-        let matching_engine = MatchingEngine::new(self.storage);
-        let matchings = matching_engine.get_greedy_matchings(with_subsplit);
-        println!("{:#?}", matchings);
+        let algorithm = match Select::new(
+            "Select matching algorithm",
+            Algorithm::value_variants().to_vec(),
+        )
+        .prompt()
+        {
+            Ok(algorithm) => algorithm,
+            Err(_) => {
+                println!("Invalid category");
+                return;
+            }
+        };
+
+        let ordering = if algorithm.uses_ordering() {
+            match Select::new(
+                "Select guest ordering",
+                OrderingPolicy::value_variants().to_vec(),
+            )
+            .prompt()
+            {
+                Ok(ordering) => ordering,
+                Err(_) => {
+                    println!("Invalid category");
+                    return;
+                }
+            }
+        } else {
+            OrderingPolicy::default()
+        };
+
+        let blocking_pairs = match Select::new("List blocking pairs?", vec!["yes", "no"]).prompt() {
+            Ok("yes") => true,
+            Ok("no") => false,
+            _ => {
+                println!("Invalid category");
+                return;
+            }
+        };
+
+        let weights = if algorithm.uses_weights() || blocking_pairs {
+            let Some(weights) = Self::inquire_weights() else {
+                return;
+            };
+            weights
+        } else {
+            ScoringWeights::default()
+        };
+
+        let incremental = match Select::new(
+            "Keep existing contracts and store the new ones?",
+            vec!["yes", "no"],
+        )
+        .prompt()
+        {
+            Ok("yes") => true,
+            Ok("no") => false,
+            _ => {
+                println!("Invalid category");
+                return;
+            }
+        };
+
+        let renewal = match Select::new("Renew contracts when they end?", vec!["yes", "no"])
+            .prompt()
+        {
+            Ok("yes") => match CustomType::new("Price indexation at every renewal, in percent:")
+                .with_default(0.0)
+                .prompt()
+            {
+                Ok(indexation_percent) => {
+                    let renewal = RenewalPolicy::new(indexation_percent);
+                    if let Err(e) = renewal.validate() {
+                        println!("Invalid indexation: {}", e);
+                        return;
+                    }
+                    Some(renewal)
+                }
+                Err(_) => {
+                    println!("Invalid indexation");
+                    return;
+                }
+            },
+            Ok("no") => None,
+            _ => {
+                println!("Invalid category");
+                return;
+            }
+        };
+
+        let default_horizon = MatchingHorizon::default();
+
+        let start_date = CustomType::new("First period starts on (YYYY-MM-DD):")
+            .with_default(default_horizon.start_date)
+            .prompt();
+
+        let nb_periods = CustomType::new("Number of periods:")
+            .with_default(default_horizon.nb_periods)
+            .prompt();
+
+        let period_months = CustomType::new("Period length in months:")
+            .with_default(default_horizon.period_months)
+            .prompt();
+
+        let horizon = match (start_date, nb_periods, period_months) {
+            (Ok(start_date), Ok(nb_periods), Ok(period_months)) => {
+                let horizon =
+                    MatchingHorizon::new(start_date, nb_periods).with_period_months(period_months);
+                if let Err(e) = horizon.validate() {
+                    println!("Invalid horizon: {}", e);
+                    return;
+                }
+                horizon
+            }
+            _ => {
+                println!("Invalid horizon");
+                return;
+            }
+        };
+
+        let Some(format) = Self::inquire_output_format() else {
+            return;
+        };
+
+        let mut matching_engine = MatchingEngine::new(self.storage)
+            .with_horizon(horizon)
+            .with_ordering(ordering)
+            .with_incremental(incremental);
+        if blocking_pairs {
+            matching_engine = matching_engine.with_stability_check(StableMatching::new(weights));
+        }
+        if let Some(renewal) = renewal {
+            matching_engine = matching_engine.with_renewal(renewal);
+        }
+        let matchings = matching_engine.get_matchings(algorithm, with_subsplit, weights);
+        Self::print(&matchings, format);
+
+        println!("Report:\n");
+        Self::print(&MatchingReport::new(self.storage, &matchings), format);
+
+        if incremental {
+            self.storage.store_matchings(&matchings);
+        }
     }
 
     fn add_user(&mut self, user_json: &str) -> anyhow::Result<()> {
         let request: AddUserRequest = serde_json::from_str(user_json)?;
-        let user = User::new(request)?;
-        self.storage.add_user(user);
+        self.storage.create_user(request)?;
         Ok(())
     }
 
     fn add_rental_space(&mut self, rental_space_json: &str) -> anyhow::Result<()> {
         let request: AddRentalSpaceRequest = serde_json::from_str(rental_space_json)?;
-        let user = self
-            .storage
-            .get_user(&request.owner_id)
-            .ok_or(anyhow::anyhow!(
-                "User with id {} not found",
-                request.owner_id
-            ))?;
-        let rental_space = RentalSpace::new(request, user.id().clone())?;
-        self.storage.add_rental_space(rental_space);
+        self.storage.create_rental_space(request)?;
         Ok(())
     }
 
-    fn print_users(&self) {
-        for user in &self.storage.users() {
-            println!("{:#?}\n", user);
+    fn print_users(&self, format: OutputFormat) {
+        Self::print_list(&self.storage.users(), format);
+    }
+
+    fn print_rental_spaces(&self, format: OutputFormat) {
+        Self::print_list(&self.storage.rental_spaces(), format);
+    }
+
+    fn print_list<T: fmt::Debug + Serialize>(objects: &[&T], format: OutputFormat) {
+        match format {
+            OutputFormat::Text => {
+                for object in objects {
+                    Self::print(object, format);
+                }
+            }
+            OutputFormat::Json => Self::print(&objects, format),
+        }
+    }
+
+    fn print<T: fmt::Debug + Serialize>(object: &T, format: OutputFormat) {
+        match format {
+            OutputFormat::Text => println!("{:#?}\n", object),
+            OutputFormat::Json => match serde_json::to_string_pretty(object) {
+                Ok(json) => println!("{}\n", json),
+                Err(e) => println!("Error serializing object: {}", e),
+            },
+        }
+    }
+
+    fn inquire_weights() -> Option<ScoringWeights> {
+        let default_weights = ScoringWeights::default();
+
+        let distance = CustomType::new("Weight of the distance:")
+            .with_default(default_weights.distance)
+            .prompt();
+
+        let price = CustomType::new("Weight of the price:")
+            .with_default(default_weights.price)
+            .prompt();
+
+        let waste = CustomType::new("Weight of the wasted workstations:")
+            .with_default(default_weights.waste)
+            .prompt();
+
+        let amenities = CustomType::new("Weight of the missing amenities:")
+            .with_default(default_weights.amenities)
+            .prompt();
+
+        match (distance, price, waste, amenities) {
+            (Ok(distance), Ok(price), Ok(waste), Ok(amenities)) => Some(ScoringWeights {
+                distance,
+                price,
+                waste,
+                amenities,
+            }),
+            _ => {
+                println!("Invalid weights");
+                None
+            }
         }
     }
 
-    fn print_rental_spaces(&self) {
-        for rental_space in &self.storage.rental_spaces() {
-            println!("{:#?}\n", rental_space);
+    fn inquire_output_format() -> Option<OutputFormat> {
+        let formats: Vec<&str> = vec!["text", "json"];
+
+        let ans: Result<&str, InquireError> = Select::new("Select output format", formats).prompt();
+
+        match ans {
+            Ok("text") => Some(OutputFormat::Text),
+            Ok("json") => Some(OutputFormat::Json),
+            _ => {
+                println!("Invalid output format");
+                None
+            }
         }
     }
 
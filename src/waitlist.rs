@@ -0,0 +1,104 @@
+use crate::{
+    matching::{Matchings, UnmatchedReason},
+    user::{UserId, WorkspaceRequest},
+};
+
+use {
+    chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
+    std::collections::HashSet,
+    validator::Validate,
+};
+
+/// Guests left unmatched, in the order in which they are offered the
+/// workstations that become available. Guests keep their position when they
+/// are left unmatched again, and leave the queue once matched.
+#[derive(Debug, Default, Clone)]
+pub struct Waitlist {
+    entries: Vec<WaitlistEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct WaitlistEntry {
+    pub user_id: UserId,
+    /// Why the guest could not be matched the last time they were considered.
+    pub reason: UnmatchedReason,
+    pub queued_at: DateTime<Utc>,
+    /// The last time the guest was considered.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Entry of the waitlist along with its position, starting at 1, and the
+/// request of the guest.
+#[derive(Debug, Serialize)]
+pub struct WaitlistPosition<'a> {
+    pub position: usize,
+    #[serde(flatten)]
+    pub entry: &'a WaitlistEntry,
+    pub workspace_request: Option<&'a WorkspaceRequest>,
+}
+
+impl Waitlist {
+    /// Returns the entries in the order of the queue.
+    pub fn entries(&self) -> &[WaitlistEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the position of the guest in the queue, starting at 1.
+    pub fn position(&self, user_id: &UserId) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| &entry.user_id == user_id)
+            .map(|index| index + 1)
+    }
+
+    /// Adds `entry` at the end of the queue, replacing the entry of the same
+    /// guest if any.
+    pub fn push(&mut self, entry: WaitlistEntry) {
+        self.remove(&entry.user_id);
+        self.entries.push(entry);
+    }
+
+    pub fn remove(&mut self, user_id: &UserId) -> Option<WaitlistEntry> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| &entry.user_id == user_id)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Queues the guests left unmatched by `matchings` after the ones already
+    /// waiting, in the order of the matchings, and removes the guests it
+    /// matched.
+    pub fn update(&mut self, matchings: &Matchings, now: DateTime<Utc>) {
+        let matched: HashSet<&UserId> = matchings
+            .contracts()
+            .map(|contract| contract.guest_id())
+            .collect();
+        self.entries
+            .retain(|entry| !matched.contains(&entry.user_id));
+
+        for unmatched in &matchings.unmatched_users {
+            match self
+                .entries
+                .iter_mut()
+                .find(|entry| entry.user_id == unmatched.user_id)
+            {
+                Some(entry) => {
+                    entry.reason = unmatched.reason;
+                    entry.updated_at = now;
+                }
+                None => self.entries.push(WaitlistEntry {
+                    user_id: unmatched.user_id.clone(),
+                    reason: unmatched.reason,
+                    queued_at: now,
+                    updated_at: now,
+                }),
+            }
+        }
+    }
+}